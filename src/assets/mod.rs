@@ -0,0 +1,74 @@
+//! Provides the static asset pipeline, copying non-Cobalt files into the output directory.
+
+use std::{
+    ffi::OsStr,
+    path::{Path, PathBuf},
+};
+
+use colored::*;
+
+use crate::{
+    build::Build,
+    files,
+    ignore,
+    log,
+    stage::Stage,
+};
+
+
+/// Holds a summary of the files handled by the asset pipeline.
+#[derive(Default)]
+pub struct Report {
+    pub copied: Vec<PathBuf>,
+    pub unchanged: Vec<PathBuf>,
+}
+
+impl Report {
+    /// Prints the copied files if verbose and a summary line.
+    pub fn print(&self) {
+        for path in &self.copied {
+            log::verbose(&format!("{} {}", "  copied".green(), path.display()));
+        }
+        log::info(&format!(
+            "{} {} asset(s), {} unchanged",
+            "Copied".green().bold(),
+            self.copied.len(),
+            self.unchanged.len(),
+        ));
+    }
+}
+
+
+/// Stages all changed assets from the source tree and the static directory for the output directory.
+pub fn copy(build: &Build, stage: &Stage) -> Report {
+    let mut report = Report::default();
+
+    // Copy assets that live alongside the source files.
+    let source = build.get_source();
+    for (path, relative) in ignore::walk(source, build.get_rules(), |p| build.is_reserved(p)) {
+        if path.extension() == Some(OsStr::new("co")) || path == source.join("cobalt.toml") {
+            continue;
+        }
+        copy_file(&path, &relative, build.get_output(), stage, &mut report);
+    }
+
+    // Copy the contents of the static directory into the root of the output directory.
+    if let Some(s) = build.get_static() {
+        for (path, relative) in ignore::walk(s, build.get_rules(), |p| build.get_output() == p) {
+            copy_file(&path, &relative, build.get_output(), stage, &mut report);
+        }
+    }
+
+    report
+}
+
+
+/// Stages a single asset unless it is unchanged and records the result.
+fn copy_file(path: &Path, relative: &Path, output: &Path, stage: &Stage, report: &mut Report) {
+    if files::unchanged(path, &output.join(relative)) {
+        report.unchanged.push(relative.to_path_buf());
+    } else {
+        stage.copy(path, relative);
+        report.copied.push(relative.to_path_buf());
+    }
+}
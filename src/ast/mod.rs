@@ -0,0 +1,223 @@
+//! Provides the versioned JSON representation of tokens and syntax trees.
+//! 
+//! Documents are objects holding the schema `version` alongside their contents, e.g.
+//! `{"version": 1, "front_matter": {}, "expressions": [{"type": "h1", "value": "Title"}]}`.
+//! The version is incremented whenever the representation changes incompatibly.
+
+use serde_derive::{Serialize, Deserialize};
+use toml::value::Table;
+
+use crate::{
+    tokenizer::Token,
+    parser::Expression,
+    error::{throw, Error},
+};
+
+
+/// Holds the version of the JSON representation.
+pub const VERSION: u32 = 1;
+
+
+/// Holds the syntax tree of a page.
+#[derive(Serialize, Deserialize)]
+pub struct Ast {
+    pub version: u32,
+    pub front_matter: Table,
+    pub expressions: Vec<Expression>,
+}
+
+impl Ast {
+    /// Constructs the syntax tree of a page in the current version.
+    pub fn new(front_matter: Table, expressions: Vec<Expression>) -> Self {
+        Self {
+            version: VERSION,
+            front_matter,
+            expressions,
+        }
+    }
+
+    /// Reads a syntax tree from JSON, throwing an error if its version is not supported.
+    pub fn from_json(json: &str) -> Self {
+        check_version(json);
+
+        match serde_json::from_str(json) {
+            Ok(a) => a,
+            Err(e) => throw(Error::InvalidJson (e.to_string())),
+        }
+    }
+
+    /// Writes the syntax tree as pretty-printed JSON.
+    pub fn to_json(&self) -> String {
+        to_json(self)
+    }
+}
+
+
+/// Holds the tokens of a page.
+#[derive(Serialize, Deserialize)]
+pub struct Tokens {
+    pub version: u32,
+    pub tokens: Vec<Token>,
+}
+
+impl Tokens {
+    /// Constructs a list of tokens in the current version.
+    pub fn new(tokens: Vec<Token>) -> Self {
+        Self {
+            version: VERSION,
+            tokens,
+        }
+    }
+
+    /// Reads tokens from JSON, throwing an error if their version is not supported.
+    pub fn from_json(json: &str) -> Self {
+        check_version(json);
+
+        match serde_json::from_str(json) {
+            Ok(t) => t,
+            Err(e) => throw(Error::InvalidJson (e.to_string())),
+        }
+    }
+
+    /// Writes the tokens as pretty-printed JSON.
+    pub fn to_json(&self) -> String {
+        to_json(self)
+    }
+}
+
+
+/// Checks the version of a JSON document before reading the rest of it.
+fn check_version(json: &str) {
+    #[derive(Deserialize)]
+    struct Versioned {
+        version: u32,
+    }
+
+    match serde_json::from_str::<Versioned>(json) {
+        Ok(v) if v.version == VERSION => (),
+        Ok(v) => throw(Error::UnsupportedVersion (v.version)),
+        Err(e) => throw(Error::InvalidJson (e.to_string())),
+    }
+}
+
+
+/// Writes a value as pretty-printed JSON, ending with a newline.
+fn to_json<T: serde::Serialize>(value: &T) -> String {
+    match serde_json::to_string_pretty(value) {
+        Ok(j) => format!("{}\n", j),
+        Err(e) => throw(Error::InvalidJson (e.to_string())),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use crate::{
+        error::catch,
+        tokenizer::TokenType,
+    };
+
+    fn expressions() -> Vec<Expression> {
+        vec![
+            Expression::Ctrl {
+                keyword: "image".to_string(),
+                class: Some("wide".to_string()),
+                id: None,
+                argument: "a.png".to_string(),
+            },
+            Expression::Paragraph ("Hello".to_string()),
+            Expression::Hyperlink {
+                text: "Home".to_string(),
+                href: "/index.co".to_string(),
+            },
+            Expression::H1 ("One".to_string()),
+            Expression::H2 ("Two".to_string()),
+            Expression::H3 ("Three".to_string()),
+            Expression::H4 ("Four".to_string()),
+            Expression::H5 ("Five".to_string()),
+            Expression::H6 ("Six".to_string()),
+            Expression::Fixed (vec![Expression::Paragraph ("Fixed".to_string())]),
+            Expression::Html ("<hr>".to_string()),
+            Expression::Custom {
+                name: "note".to_string(),
+                value: "tip".to_string(),
+                children: vec![Expression::Paragraph ("Inside".to_string())],
+            },
+        ]
+    }
+
+    #[test]
+    fn ast_json_shape_is_stable() {
+        let mut front_matter = Table::new();
+        front_matter.insert("title".to_string(), toml::Value::String ("Home".to_string()));
+
+        let json: serde_json::Value = serde_json::from_str(&Ast::new(front_matter, expressions()).to_json()).unwrap();
+        assert_eq!(json, json!({
+            "version": 1,
+            "front_matter": {"title": "Home"},
+            "expressions": [
+                {"type": "ctrl", "value": {"keyword": "image", "class": "wide", "id": null, "argument": "a.png"}},
+                {"type": "paragraph", "value": "Hello"},
+                {"type": "hyperlink", "value": {"text": "Home", "href": "/index.co"}},
+                {"type": "h1", "value": "One"},
+                {"type": "h2", "value": "Two"},
+                {"type": "h3", "value": "Three"},
+                {"type": "h4", "value": "Four"},
+                {"type": "h5", "value": "Five"},
+                {"type": "h6", "value": "Six"},
+                {"type": "fixed", "value": [{"type": "paragraph", "value": "Fixed"}]},
+                {"type": "html", "value": "<hr>"},
+                {"type": "custom", "value": {
+                    "name": "note",
+                    "value": "tip",
+                    "children": [{"type": "paragraph", "value": "Inside"}],
+                }},
+            ],
+        }));
+    }
+
+    #[test]
+    fn ast_round_trips() {
+        let json = Ast::new(Table::new(), expressions()).to_json();
+        let ast = Ast::from_json(&json);
+        assert_eq!(ast.version, VERSION);
+        assert_eq!(format!("{:?}", ast.expressions), format!("{:?}", expressions()));
+        assert_eq!(ast.to_json(), json);
+    }
+
+    #[test]
+    fn tokens_json_shape_is_stable_and_round_trips() {
+        let tokens = Tokens::new(vec![
+            Token::new(TokenType::H1, "Title".to_string()),
+            Token::new(TokenType::Ctrl, "image.wide".to_string()),
+            Token::new(TokenType::Brace, "a.png".to_string()),
+            Token::new(TokenType::Custom ("note".to_string()), "tip".to_string()),
+        ]);
+
+        let json = tokens.to_json();
+        assert_eq!(serde_json::from_str::<serde_json::Value>(&json).unwrap(), json!({
+            "version": 1,
+            "tokens": [
+                {"type": "h1", "value": "Title"},
+                {"type": "ctrl", "value": "image.wide"},
+                {"type": "brace", "value": "a.png"},
+                {"type": {"custom": "note"}, "value": "tip"},
+            ],
+        }));
+        assert_eq!(Tokens::from_json(&json).to_json(), json);
+    }
+
+    #[test]
+    fn other_versions_are_unsupported() {
+        let ast = catch(|| Ast::from_json(r#"{"version": 2, "front_matter": {}, "expressions": []}"#));
+        assert!(matches!(ast, Err(Error::UnsupportedVersion (2))));
+
+        let tokens = catch(|| Tokens::from_json(r#"{"version": 2, "tokens": []}"#));
+        assert!(matches!(tokens, Err(Error::UnsupportedVersion (2))));
+
+        let invalid = catch(|| Ast::from_json(r#"{"expressions": []}"#));
+        assert!(matches!(invalid, Err(Error::InvalidJson (_))));
+    }
+}
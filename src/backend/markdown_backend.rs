@@ -0,0 +1,129 @@
+//! Provides a backend rendering pages into Markdown.
+
+use crate::{
+    parser::Expression,
+    emitter::Emitter,
+    backend::Backend,
+    cache::Dependency,
+};
+
+/// Renders pages into Markdown, falling back to inline HTML for sequences Markdown lacks.
+pub struct MarkdownBackend<'a> {
+    emitter: Emitter<'a>,
+}
+
+impl<'a> MarkdownBackend<'a> {
+    /// Constructs a new Markdown backend around an emitter, which resolves URLs and links
+    /// and emits shortcodes and custom expressions as inline HTML.
+    pub fn new(mut emitter: Emitter<'a>) -> Self {
+        emitter.set_extension("md");
+
+        Self {
+            emitter,
+        }
+    }
+
+    /// Renders an expression into Markdown.
+    /// 
+    /// `paragraph` holds whether the previous expression was a paragraph, in which case a
+    /// following paragraph begins a new block.  Paragraphs around hyperlinks remain inline.
+    fn emit_expr(&self, expr: Expression, markdown: &mut String, paragraph: &mut bool) {
+        let previous = std::mem::replace(paragraph, false);
+
+        match expr {
+            Expression::Ctrl {
+                keyword: k,
+                ..
+            } if ["pagename", "layout"].contains(&k.as_str()) => (),
+            Expression::Ctrl {
+                keyword: k,
+                argument: a,
+                ..
+            } if k == "image" => markdown.push_str(&format!("![]({})", self.emitter.url(&a))),
+            Expression::Ctrl {
+                keyword: k,
+                argument: a,
+                ..
+            } if k == "download" => markdown.push_str(&format!("[Download]({})", self.emitter.url(&a))),
+            Expression::Paragraph (s) => {
+                if previous {
+                    separate(markdown);
+                }
+                markdown.push_str(&s);
+                *paragraph = true;
+            },
+            Expression::Hyperlink {
+                text: t,
+                href: h,
+            } => markdown.push_str(&format!("[{}]({})", t, self.emitter.link(&h))),
+            Expression::H1 (s) => heading(markdown, 1, &s),
+            Expression::H2 (s) => heading(markdown, 2, &s),
+            Expression::H3 (s) => heading(markdown, 3, &s),
+            Expression::H4 (s) => heading(markdown, 4, &s),
+            Expression::H5 (s) => heading(markdown, 5, &s),
+            Expression::H6 (s) => heading(markdown, 6, &s),
+            Expression::Fixed (expressions) => {
+                separate(markdown);
+                for expr in expressions {
+                    self.emit_expr(expr, markdown, paragraph);
+                }
+                separate(markdown);
+                *paragraph = false;
+            },
+            Expression::Html (s) => markdown.push_str(&s),
+            e => markdown.push_str(&self.emitter.emit_fragment(vec![e])),
+        }
+    }
+}
+
+impl Backend for MarkdownBackend<'_> {
+    /// Renders a page, preceded by front matter holding its title if it has one.
+    /// 
+    /// As in HTML, the last `\pagename{...}` of a page takes precedence.
+    fn emit(&self, expressions: Vec<Expression>) -> String {
+        let title = expressions.iter().rev().find_map(|e| match e {
+            Expression::Ctrl { keyword: k, argument: a, .. } if k == "pagename" => Some(a.to_owned()),
+            _ => None,
+        });
+
+        let body = self.emit_fragment(expressions);
+        match title {
+            Some(t) => format!("---\ntitle: {}\n---\n\n{}", t, body),
+            None => body,
+        }
+    }
+
+    fn emit_fragment(&self, expressions: Vec<Expression>) -> String {
+        let mut markdown = String::new();
+        let mut paragraph = false;
+
+        for expr in expressions {
+            self.emit_expr(expr, &mut markdown, &mut paragraph);
+        }
+
+        let mut markdown = markdown.trim().to_string();
+        markdown.push('\n');
+        markdown
+    }
+
+    fn dependencies(&self) -> Vec<Dependency> {
+        self.emitter.dependencies()
+    }
+}
+
+
+/// Ends the current block with a blank line, unless nothing precedes it.
+fn separate(markdown: &mut String) {
+    let trimmed = markdown.trim_end_matches([' ', '\n']).len();
+    markdown.truncate(trimmed);
+    if !markdown.is_empty() {
+        markdown.push_str("\n\n");
+    }
+}
+
+
+/// Pushes a heading onto its own line, separated from the preceding text by a blank line.
+fn heading(markdown: &mut String, level: usize, text: &str) {
+    separate(markdown);
+    markdown.push_str(&format!("{} {}\n\n", "#".repeat(level), text.trim()));
+}
@@ -0,0 +1,54 @@
+//! Provides output backends, which render parsed pages into a particular format.
+//! 
+//! The HTML `Emitter` is the default backend.  Others wrap it, reusing its resolution
+//! of URLs and links to other pages.
+
+pub mod markdown_backend;
+
+use crate::{
+    parser::Expression,
+    error::{throw, Error},
+    cache::Dependency,
+};
+
+pub use markdown_backend::MarkdownBackend;
+
+
+/// Renders the expressions of a page into an output format.
+pub trait Backend {
+    /// Renders a complete page.
+    fn emit(&self, expressions: Vec<Expression>) -> String;
+
+    /// Renders the body of a page only, without its surrounding shell.
+    fn emit_fragment(&self, expressions: Vec<Expression>) -> String;
+
+    /// Gets the files that the pages rendered so far depend on.
+    fn dependencies(&self) -> Vec<Dependency>;
+}
+
+
+/// Enumerates the output formats of the built-in backends.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Format {
+    Html,
+    Markdown,
+}
+
+impl Format {
+    /// Gets a format from its name on the command line.
+    pub fn from_name(name: &str) -> Self {
+        match name {
+            "html" => Self::Html,
+            "markdown" | "md" => Self::Markdown,
+            _ => throw(Error::InvalidFormat (name.to_string())),
+        }
+    }
+
+    /// Gets the file extension of pages in this format.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Html => "html",
+            Self::Markdown => "md",
+        }
+    }
+}
@@ -0,0 +1,676 @@
+//! Provides the site build: discovers source files and writes their output.
+
+use std::{
+    ffi::OsStr,
+    fs,
+    panic,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+        Mutex,
+    },
+    thread,
+    time::Instant,
+};
+
+use colored::*;
+
+use crate::{
+    Config,
+    emitter::{self, Emitter},
+    backend::{Backend, Format, MarkdownBackend},
+    error::{throw, catch, Error},
+    files::{read, hash, hash_file, absolute},
+    assets,
+    ignore::{self, Rules},
+    include,
+    log,
+    manifest::{self, Manifest},
+    cache::{self, Cache, Dependency, Entry},
+    stage::{self, Stage},
+    syntax::Syntax,
+    pass::{Pass, Pipeline, Context},
+    parser::Expression,
+    vars,
+    plugin,
+    data::Data,
+    sitemap::{self, SITEMAP},
+};
+
+
+/// Holds the directories and configuration of a single site build.
+pub struct Build {
+    config: Config,
+    root: PathBuf,
+    source: PathBuf,
+    output: PathBuf,
+    static_dir: Option<PathBuf>,
+    layouts: PathBuf,
+    shortcodes: PathBuf,
+    data: PathBuf,
+    data_tree: Mutex<Option<Arc<Data>>>,
+    rules: Rules,
+    syntax: Syntax,
+    pipeline: Pipeline,
+    format: Format,
+    jobs: usize,
+    dry_run: bool,
+    checking: AtomicBool,
+    plugins: Vec<PathBuf>,
+}
+
+impl Build {
+    /// Constructs a new build from a configuration and the directory containing `cobalt.toml`.
+    /// 
+    /// An output directory passed on the command line takes precedence over `site.output`.
+    /// Relative directories are resolved against the current working directory, so that
+    /// they can be compared with the paths of discovered files.
+    pub fn new(config: Config, root: &Path, output: Option<PathBuf>) -> Self {
+        let root = &absolute(root);
+        let source = match &config.site.path {
+            Some(s) => root.join(s),
+            None => root.to_path_buf(),
+        };
+
+        let output = match output {
+            Some(o) => absolute(&o),
+            None => match &config.site.output {
+                Some(o) => root.join(o),
+                None => source.clone(),
+            },
+        };
+
+        let static_dir = match &config.site.static_dir {
+            Some(s) => Some(root.join(s)),
+            None => Some(root.join("static")).filter(|s| s.is_dir()),
+        };
+
+        let layouts = root.join(config.site.layouts.as_deref().unwrap_or("layouts"));
+
+        let shortcodes = root.join("shortcodes");
+        let data = root.join("data");
+
+        let plugins = match &config.plugins {
+            Some(p) => p.values().filter_map(|p| plugin::local(p.get_command(), root)).collect(),
+            None => Vec::new(),
+        };
+
+        let rules = Rules::new(&config, root);
+        let pipeline = Pipeline::new(&config);
+
+        Self {
+            config,
+            root: root.to_path_buf(),
+            source,
+            output,
+            static_dir,
+            layouts,
+            shortcodes,
+            data,
+            data_tree: Mutex::new(None),
+            rules,
+            syntax: Syntax::new(),
+            pipeline,
+            format: Format::Html,
+            jobs: thread::available_parallelism().map_or(1, |n| n.get()),
+            dry_run: false,
+            checking: AtomicBool::new(false),
+            plugins,
+        }
+    }
+
+    /// Sets whether builds only print the files they would write and delete.
+    pub fn set_dry_run(&mut self, dry_run: bool) {
+        self.dry_run = dry_run;
+    }
+
+    /// Sets the syntax extensions used to compile pages.
+    pub fn set_syntax(&mut self, syntax: Syntax) {
+        self.syntax = syntax;
+    }
+
+    /// Adds a pass run on every page after the passes configured in `cobalt.toml`.
+    pub fn add_pass(&mut self, pass: Box<dyn Pass>) {
+        self.pipeline.push(pass);
+    }
+
+    /// Sets the output format of pages.
+    pub fn set_format(&mut self, format: Format) {
+        self.format = format;
+    }
+
+    /// Sets the number of pages compiled concurrently.
+    pub fn set_jobs(&mut self, jobs: usize) {
+        self.jobs = jobs.max(1);
+    }
+
+    /// Checks whether builds only print the files they would write and delete.
+    pub fn is_dry_run(&self) -> bool {
+        self.dry_run
+    }
+
+    /// Checks whether the site is only being checked for errors, in which case nothing is written.
+    pub fn is_checking(&self) -> bool {
+        self.checking.load(Ordering::Relaxed)
+    }
+
+    /// Gets the site configuration.
+    pub fn get_config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Gets the directory containing `cobalt.toml`.
+    pub fn get_root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Gets the source directory.
+    pub fn get_source(&self) -> &Path {
+        &self.source
+    }
+
+    /// Gets the output directory.
+    pub fn get_output(&self) -> &Path {
+        &self.output
+    }
+
+    /// Gets the static asset directory, if any.
+    pub fn get_static(&self) -> Option<&Path> {
+        self.static_dir.as_deref()
+    }
+
+    /// Gets the layout directory.
+    pub fn get_layouts(&self) -> &Path {
+        &self.layouts
+    }
+
+    /// Gets the directory of shortcode templates.
+    pub fn get_shortcodes(&self) -> &Path {
+        &self.shortcodes
+    }
+
+    /// Gets the data directory.
+    pub fn get_data(&self) -> &Path {
+        &self.data
+    }
+
+    /// Gets the data tree, loading it the first time a page of the current build uses it.
+    pub fn get_data_tree(&self) -> Arc<Data> {
+        let mut data = match self.data_tree.lock() {
+            Ok(d) => d,
+            Err(e) => e.into_inner(),
+        };
+        data.get_or_insert_with(|| Arc::new(Data::load(&self.data))).clone()
+    }
+
+    /// Discards the data tree, so that the next build reloads it.
+    fn reload_data(&self) {
+        match self.data_tree.lock() {
+            Ok(mut d) => *d = None,
+            Err(e) => *e.into_inner() = None,
+        }
+    }
+
+    /// Gets the rules deciding which files are skipped.
+    pub fn get_rules(&self) -> &Rules {
+        &self.rules
+    }
+
+    /// Recursively walks through the source directory and returns the
+    /// paths of all source files except partials, relative to the source directory.
+    pub fn pages(&self) -> Vec<PathBuf> {
+        ignore::walk(&self.source, &self.rules, |p| self.is_reserved(p))
+            .into_iter()
+            .filter(|(p, r)| p.extension() == Some(OsStr::new("co")) && !include::is_partial(r))
+            .map(|(_, r)| r)
+            .collect()
+    }
+
+    /// Checks whether a path lies within a separate output directory, the staging directory,
+    /// the static directory, the layout, shortcode or data directory, or is a plugin executable,
+    /// none of which is a source file or an asset.
+    pub fn is_reserved(&self, path: &Path) -> bool {
+        if self.plugins.iter().any(|p| p == path) {
+            return true;
+        }
+
+        let output = (self.output != self.source && path.starts_with(&self.output))
+            || path.starts_with(self.output.join(stage::STAGING));
+        let static_dir = match &self.static_dir {
+            Some(s) => path.starts_with(s),
+            None => false,
+        };
+        let directories = [&self.layouts, &self.shortcodes, &self.data];
+        output || static_dir || directories.iter().any(|d| path.starts_with(d))
+    }
+
+    /// Checks whether a path relative to the source directory is a page compiled by builds:
+    /// an existing source file that is neither a partial, skipped by the rules nor reserved.
+    pub fn is_page(&self, page: &Path) -> bool {
+        let path = self.source.join(page);
+
+        page.extension() == Some(OsStr::new("co"))
+            && path.is_file()
+            && !include::is_partial(page)
+            && !self.rules.is_excluded(page)
+            && !self.is_reserved(&path)
+    }
+
+    /// Gets the output path of a page, mirroring its location in the source directory.
+    pub fn output_path(&self, page: &Path) -> PathBuf {
+        self.output.join(output_file(page, self.pretty_urls(), self.format.extension()))
+    }
+
+    /// Checks whether the site uses pretty URLs.
+    pub fn pretty_urls(&self) -> bool {
+        self.config.site.pretty_urls.unwrap_or(false)
+    }
+
+    /// Compiles a single page, given relative to the source directory, in the output format.
+    pub fn compile(&self, page: &Path) -> String {
+        self.render(page, read(&self.source.join(page)), false)
+    }
+
+    /// Compiles Cobalt source in the output format as if it were the given page.
+    /// 
+    /// If `fragment` is set, only the body of the page is emitted.
+    pub fn render(&self, page: &Path, data: String, fragment: bool) -> String {
+        self.render_with_dependencies(page, data, fragment).0
+    }
+
+    /// Tokenizes and parses Cobalt source, accounting for syntax extensions.
+    pub fn parse(&self, data: String) -> Vec<Expression> {
+        self.syntax.parse(data)
+    }
+
+    /// Compiles Cobalt source in the output format, also returning the files the page depends on.
+    fn render_with_dependencies(&self, page: &Path, data: String, fragment: bool) -> (String, Vec<Dependency>) {
+        let (front_matter, data) = vars::front_matter(&data);
+        let expressions = self.parse(data.to_string());
+
+        let context = Context::new(self, page, &front_matter);
+        let expressions = self.pipeline.run(expressions, &context);
+
+        let mut emitter = Emitter::new(&self.config, &self.source, page);
+        emitter.set_layouts(&self.layouts);
+        emitter.set_shortcodes(&self.shortcodes);
+        emitter.set_build(self);
+        self.syntax.register_hooks(&mut emitter);
+        for dependency in context.dependencies() {
+            emitter.depend(dependency);
+        }
+        let backend: Box<dyn Backend> = match self.format {
+            Format::Html => Box::new(emitter),
+            Format::Markdown => Box::new(MarkdownBackend::new(emitter)),
+        };
+        let output = if fragment {
+            backend.emit_fragment(expressions)
+        } else {
+            backend.emit(expressions)
+        };
+
+        (output, backend.dependencies())
+    }
+
+    /// Builds a single page unless the cache shows it is unchanged.
+    /// 
+    /// The output is only staged if its contents changed, preserving its modification time.
+    /// Returns whether the output was staged and the new cache entry of the page.
+    fn build_page(&self, page: &Path, previous: &Cache, stage: &Stage) -> (bool, Entry) {
+        let data = read(&self.source.join(page));
+        let source = hash(data.as_bytes());
+        let output_path = self.output_path(page);
+
+        if let Some(entry) = previous.get(page) {
+            if entry.is_fresh(source) && hash_file(&output_path) == Some(entry.output) {
+                return (false, entry.to_owned());
+            }
+        }
+
+        let (html, dependencies) = self.render_with_dependencies(page, data, false);
+        let output = hash(html.as_bytes());
+
+        let written = hash_file(&output_path) != Some(output);
+        if written {
+            stage.write(&output_file(page, self.pretty_urls(), self.format.extension()), html);
+        }
+
+        (written, Entry {
+            source,
+            output,
+            dependencies: dependencies
+                .into_iter()
+                .map(|d| {
+                    let fingerprint = d.fingerprint();
+                    (d, fingerprint)
+                })
+                .collect(),
+        })
+    }
+
+    /// Builds pages concurrently, returning the results in the order of the pages.
+    /// 
+    /// If any page fails, the error of the first failing page in that order is thrown.
+    fn build_pages(&self, pages: &[PathBuf], previous: &Cache, stage: &Stage) -> Vec<(bool, Entry)> {
+        let results = parallel(pages, self.jobs, |page| self.build_page(page, previous, stage));
+
+        let mut built = Vec::new();
+        for (page, result) in pages.iter().zip(results) {
+            match result {
+                Ok(b) => built.push(b),
+                Err(e) => throw(Error::InPage (page.display().to_string(), Box::new(e))),
+            }
+        }
+
+        built
+    }
+
+    /// Gets the path of a source file relative to the source directory.
+    /// 
+    /// Files outside of the source directory are treated as if they were at its root.
+    pub fn page_of(&self, file: &Path) -> PathBuf {
+        let absolute = fs::canonicalize(file).unwrap_or(file.to_path_buf());
+        let source = fs::canonicalize(&self.source).unwrap_or(self.source.clone());
+
+        match absolute.strip_prefix(&source) {
+            Ok(p) => p.to_path_buf(),
+            Err(_) => match file.file_name() {
+                Some(n) => PathBuf::from(n),
+                None => PathBuf::from("index.co"),
+            },
+        }
+    }
+
+    /// Compiles every source file and writes the resulting HTML.
+    /// 
+    /// Outputs are staged and only moved into the output directory once every page
+    /// has compiled.  Outputs of previous builds whose sources no longer exist are deleted.
+    pub fn run(&self) {
+        let start = Instant::now();
+        self.reload_data();
+        let pages = self.pages();
+
+        let previous = Manifest::load(&self.output);
+        let mut manifest = Manifest::new();
+
+        let previous_cache = Cache::load(&self.root, self.config.hash);
+        let mut cache = Cache::new(self.config.hash);
+        let mut written = 0;
+
+        let stage = Stage::new(&self.output, self.dry_run);
+        for (page, (w, entry)) in pages.iter().zip(self.build_pages(&pages, &previous_cache, &stage)) {
+            if w {
+                written += 1;
+            }
+            cache.insert(page, entry);
+            manifest.insert(&output_file(page, self.pretty_urls(), self.format.extension()));
+        }
+
+        if let Some(sitemap) = self.sitemap(&pages, &previous) {
+            if hash_file(&self.output.join(SITEMAP)) != Some(hash(sitemap.as_bytes())) {
+                stage.write(Path::new(SITEMAP), sitemap);
+            }
+            manifest.insert(Path::new(SITEMAP));
+        }
+
+        // Assets are already in place if the site is built in place.
+        if self.output != self.source {
+            let report = assets::copy(self, &stage);
+            for path in &report.copied {
+                manifest.insert(path);
+            }
+
+            // Unchanged assets are only Cobalt's if a previous build copied them.
+            for path in &report.unchanged {
+                if previous.contains(path) {
+                    manifest.insert(path);
+                }
+            }
+            report.print();
+        }
+
+        stage.commit();
+
+        let deleted = manifest::delete(&self.output, &previous.stale(&manifest), self.dry_run);
+        if deleted > 0 && !self.dry_run {
+            log::info(&format!("{} {} stale file(s)", "Deleted".yellow().bold(), deleted));
+        }
+        if !self.dry_run {
+            manifest.save(&self.output);
+            cache.save(&self.root);
+        }
+
+        log::info(&format!(
+            "{} {} page(s), {} unchanged, in {} ms",
+            "Built".green().bold(),
+            written,
+            pages.len() - written,
+            start.elapsed().as_millis(),
+        ));
+    }
+
+    /// Generates the sitemap of the given pages, unless the site has no absolute `site.base_url`,
+    /// is not built as HTML or provides its own `sitemap.xml`.
+    fn sitemap(&self, pages: &[PathBuf], previous: &Manifest) -> Option<String> {
+        let base_url = self.config.site.base_url.as_deref()?;
+        if !emitter::has_scheme(base_url) || self.format != Format::Html {
+            return None;
+        }
+
+        // In-place builds find the sitemap of the previous build in the source directory.
+        let in_source = self.source.join(SITEMAP).is_file()
+            && (self.output != self.source || !previous.contains(Path::new(SITEMAP)));
+        let in_static = match &self.static_dir {
+            Some(s) => s.join(SITEMAP).is_file(),
+            None => false,
+        };
+        if in_source || in_static {
+            return None;
+        }
+
+        Some(sitemap::generate(pages, base_url, self.pretty_urls()))
+    }
+
+    /// Compiles and writes only the given pages, adding them to the manifest of the last build.
+    pub fn run_pages(&self, pages: &[PathBuf]) {
+        self.reload_data();
+        let mut manifest = Manifest::load(&self.output);
+
+        let previous_cache = Cache::load(&self.root, self.config.hash);
+        let mut cache = previous_cache.clone();
+
+        // Pages including one of the given pages are rebuilt as well.
+        let mut pages = pages.to_vec();
+        let included: Vec<Dependency> = pages.iter().map(|p| Dependency::Contents (self.source.join(p))).collect();
+        for page in self.pages() {
+            let includes = match previous_cache.get(&page) {
+                Some(e) => e.dependencies.iter().any(|(d, _)| included.contains(d)),
+                None => false,
+            };
+            if includes && !pages.contains(&page) {
+                pages.push(page);
+            }
+        }
+
+        let stage = Stage::new(&self.output, self.dry_run);
+        for (page, (_, entry)) in pages.iter().zip(self.build_pages(&pages, &previous_cache, &stage)) {
+            cache.insert(page, entry);
+            manifest.insert(&output_file(page, self.pretty_urls(), self.format.extension()));
+        }
+        stage.commit();
+
+        if !self.dry_run {
+            manifest.save(&self.output);
+            cache.save(&self.root);
+        }
+    }
+
+    /// Deletes every file produced by previous builds.
+    pub fn clean(&self) {
+        let manifest = Manifest::load(&self.output);
+        let deleted = manifest::delete(&self.output, &manifest.files(), self.dry_run);
+        manifest::delete(&self.output, &[PathBuf::from(manifest::MANIFEST)], self.dry_run);
+
+        let cache = self.root.join(cache::CACHE);
+        if self.dry_run {
+            log::info(&format!("{} {}", "would delete".cyan(), cache.display()));
+        } else if cache.is_dir() && fs::remove_dir_all(&cache).is_err() {
+            throw(Error::CouldNotDeleteFile (cache.display().to_string()));
+        }
+
+        if !self.dry_run {
+            log::info(&format!("{} {} file(s)", "Deleted".yellow().bold(), deleted));
+        }
+    }
+
+    /// Compiles every source file without writing anything.
+    pub fn check(&self) {
+        self.reload_data();
+        let pages = self.pages();
+
+        self.checking.store(true, Ordering::Relaxed);
+        let results = parallel(&pages, self.jobs, |page| self.compile(page));
+        self.checking.store(false, Ordering::Relaxed);
+
+        for (page, result) in pages.iter().zip(results) {
+            if let Err(e) = result {
+                throw(Error::InPage (page.display().to_string(), Box::new(e)));
+            }
+            log::verbose(&format!("{} {}", " checked".green(), page.display()));
+        }
+
+        log::info(&format!("{} {} page(s), no errors found", "Checked".green().bold(), pages.len()));
+    }
+}
+
+
+/// Applies a function to every page on up to `jobs` threads, catching any errors thrown.
+/// 
+/// The results are returned in the order of the pages, regardless of which thread finished first.
+fn parallel<T, F>(pages: &[PathBuf], jobs: usize, f: F) -> Vec<Result<T, Error>>
+where
+    T: Send,
+    F: Fn(&Path) -> T + Sync,
+{
+    let next = AtomicUsize::new(0);
+    let mut results: Vec<(usize, Result<T, Error>)> = Vec::new();
+
+    thread::scope(|scope| {
+        let workers: Vec<_> = (0..jobs.clamp(1, pages.len().max(1)))
+            .map(|_| scope.spawn(|| {
+                let mut done = Vec::new();
+                loop {
+                    let i = next.fetch_add(1, Ordering::Relaxed);
+                    match pages.get(i) {
+                        Some(page) => done.push((i, catch(|| f(page)))),
+                        None => return done,
+                    }
+                }
+            }))
+            .collect();
+
+        for worker in workers {
+            match worker.join() {
+                Ok(done) => results.extend(done),
+                Err(e) => panic::resume_unwind(e),
+            }
+        }
+    });
+
+    results.sort_by_key(|(i, _)| *i);
+    results.into_iter().map(|(_, r)| r).collect()
+}
+
+
+/// Checks whether a page is the index of its directory.
+pub fn is_index(page: &Path) -> bool {
+    page.file_stem() == Some(OsStr::new("index"))
+}
+
+
+/// Gets the output file of a page relative to the output directory, given the extension
+/// of the output format.
+/// 
+/// With pretty URLs, `blog/post.co` is written to `blog/post/index.html`;
+/// otherwise it is written to `blog/post.html`.  `index.co` always stays in place.
+pub fn output_file(page: &Path, pretty_urls: bool, extension: &str) -> PathBuf {
+    if pretty_urls && !is_index(page) {
+        page.with_extension("").join("index").with_extension(extension)
+    } else {
+        page.with_extension(extension)
+    }
+}
+
+
+/// Gets the URL of a page relative to the root of the site.
+/// 
+/// With pretty URLs, this is the directory containing the page, e.g. `/blog/post/`;
+/// otherwise it is the output file, e.g. `/blog/post.html`.
+pub fn page_url(page: &Path, pretty_urls: bool, extension: &str) -> String {
+    let output = output_file(page, pretty_urls, extension);
+    let mut components: Vec<String> = output
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy().to_string())
+        .collect();
+
+    if pretty_urls {
+        components.pop();
+        components.push(String::new());
+    }
+
+    format!("/{}", components.join("/"))
+}
+
+
+/// Gets the relative path from a page's output back to the root of the site, e.g. `../../`.
+pub fn relative_root(page: &Path, pretty_urls: bool) -> String {
+    let depth = match page.parent() {
+        Some(p) => p.components().count(),
+        None => 0,
+    };
+
+    // With pretty URLs, a page is written one directory below its source file.
+    let depth = if pretty_urls && !is_index(page) { depth + 1 } else { depth };
+
+    "../".repeat(depth)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn output_file_mirrors_the_source() {
+        assert_eq!(output_file(Path::new("blog/post.co"), false, "html"), PathBuf::from("blog/post.html"));
+        assert_eq!(output_file(Path::new("index.co"), false, "html"), PathBuf::from("index.html"));
+        assert_eq!(output_file(Path::new("blog/post.co"), false, "md"), PathBuf::from("blog/post.md"));
+    }
+
+    #[test]
+    fn output_file_with_pretty_urls() {
+        assert_eq!(output_file(Path::new("blog/post.co"), true, "html"), PathBuf::from("blog/post/index.html"));
+        assert_eq!(output_file(Path::new("blog/index.co"), true, "html"), PathBuf::from("blog/index.html"));
+        assert_eq!(output_file(Path::new("index.co"), true, "html"), PathBuf::from("index.html"));
+    }
+
+    #[test]
+    fn page_url_follows_the_scheme() {
+        assert_eq!(page_url(Path::new("blog/post.co"), false, "html"), "/blog/post.html");
+        assert_eq!(page_url(Path::new("index.co"), false, "html"), "/index.html");
+        assert_eq!(page_url(Path::new("blog/post.co"), true, "html"), "/blog/post/");
+        assert_eq!(page_url(Path::new("blog/index.co"), true, "html"), "/blog/");
+        assert_eq!(page_url(Path::new("index.co"), true, "html"), "/");
+    }
+
+    #[test]
+    fn relative_root_climbs_to_the_site_root() {
+        assert_eq!(relative_root(Path::new("index.co"), false), "");
+        assert_eq!(relative_root(Path::new("blog/post.co"), false), "../");
+        assert_eq!(relative_root(Path::new("a/b/post.co"), false), "../../");
+        assert_eq!(relative_root(Path::new("index.co"), true), "");
+        assert_eq!(relative_root(Path::new("blog/post.co"), true), "../../");
+        assert_eq!(relative_root(Path::new("blog/index.co"), true), "../");
+    }
+}
@@ -0,0 +1,232 @@
+//! Provides the build cache, which lets Cobalt skip pages whose inputs have not changed.
+//! 
+//! The cache is keyed by the Cobalt version and the hash of the configuration, and records
+//! for each page the hash of its source, the hash of its output and its dependencies.
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use walkdir::WalkDir;
+
+use crate::{
+    files::{hash, write},
+    vars::date,
+};
+
+
+/// Holds the name of the cache directory, relative to `cobalt.toml`.
+pub const CACHE: &str = ".cobalt-cache";
+
+const PAGES: &str = "pages";
+
+
+/// Enumerates the kinds of files a page may depend on.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Debug)]
+pub enum Dependency {
+    // The page depends on the contents of a file, such as an included file.
+    Contents (PathBuf),
+
+    // The page depends only on whether a file exists, such as the target of a link.
+    Exists (PathBuf),
+
+    // The page depends on which files a directory holds, such as the data directory.
+    Listing (PathBuf),
+
+    // The page depends on the date of the build, through `\var{build.date}`.
+    Date,
+}
+
+impl Dependency {
+    /// Gets the current fingerprint of a dependency.
+    pub fn fingerprint(&self) -> u64 {
+        match self {
+            Self::Contents (p) => match fs::read(p) {
+                Ok(c) => hash(&c),
+                Err(_) => 0,
+            },
+            Self::Exists (p) => p.is_file() as u64,
+            Self::Listing (p) => {
+                let mut files: Vec<String> = WalkDir::new(p)
+                    .into_iter()
+                    .filter_map(|e| e.ok())
+                    .filter(|e| e.file_type().is_file())
+                    .map(|e| e.path().display().to_string())
+                    .collect();
+                files.sort();
+                hash(files.join("\n").as_bytes())
+            },
+            Self::Date => hash(date().as_bytes()),
+        }
+    }
+
+    /// Serializes a dependency as a tagged path.
+    fn serialize(&self) -> String {
+        match self {
+            Self::Contents (p) => format!("c:{}", p.display()),
+            Self::Exists (p) => format!("e:{}", p.display()),
+            Self::Listing (p) => format!("l:{}", p.display()),
+            Self::Date => "d:".to_string(),
+        }
+    }
+
+    /// Deserializes a dependency from a tagged path.
+    fn deserialize(s: &str) -> Option<Self> {
+        match s.split_at_checked(2)? {
+            ("c:", p) => Some(Self::Contents (PathBuf::from(p))),
+            ("e:", p) => Some(Self::Exists (PathBuf::from(p))),
+            ("l:", p) => Some(Self::Listing (PathBuf::from(p))),
+            ("d:", "") => Some(Self::Date),
+            _ => None,
+        }
+    }
+}
+
+
+/// Holds the cached state of a single page.
+#[derive(Clone, Debug)]
+pub struct Entry {
+    pub source: u64,
+    pub output: u64,
+    pub dependencies: Vec<(Dependency, u64)>,
+}
+
+impl Entry {
+    /// Checks whether the page is unchanged, given the hash of its current source.
+    pub fn is_fresh(&self, source: u64) -> bool {
+        self.source == source && self.dependencies.iter().all(|(d, f)| d.fingerprint() == *f)
+    }
+}
+
+
+/// Holds the cached state of every page of the site.
+#[derive(Clone)]
+pub struct Cache {
+    key: String,
+    pages: BTreeMap<PathBuf, Entry>,
+}
+
+impl Cache {
+    /// Constructs a new, empty cache for the given configuration hash.
+    pub fn new(config: u64) -> Self {
+        Self {
+            key: format!("cobalt {} config {:016x}", env!("CARGO_PKG_VERSION"), config),
+            pages: BTreeMap::new(),
+        }
+    }
+
+    /// Loads the cache of a site from the directory containing `cobalt.toml`.
+    /// 
+    /// The cache is empty if none exists or if it was written by a different
+    /// version of Cobalt or for a different configuration.
+    pub fn load(root: &Path, config: u64) -> Self {
+        let mut cache = Self::new(config);
+
+        let file = match fs::read_to_string(root.join(CACHE).join(PAGES)) {
+            Ok(f) => f,
+            Err(_) => return cache,
+        };
+        let mut lines = file.lines();
+        if lines.next() != Some(cache.key.as_str()) {
+            return cache;
+        }
+
+        for line in lines {
+            if let Some((page, entry)) = parse_entry(line) {
+                cache.pages.insert(page, entry);
+            }
+        }
+
+        cache
+    }
+
+    /// Writes the cache into the directory containing `cobalt.toml`.
+    pub fn save(&self, root: &Path) {
+        let mut file = format!("{}\n", &self.key);
+
+        for (page, entry) in &self.pages {
+            file.push_str(&format!("{}\t{:016x}\t{:016x}", page.display(), entry.source, entry.output));
+            for (dependency, fingerprint) in &entry.dependencies {
+                file.push_str(&format!("\t{}\t{:016x}", dependency.serialize(), fingerprint));
+            }
+            file.push('\n');
+        }
+
+        write(&root.join(CACHE).join(PAGES), file);
+    }
+
+    /// Gets the cached state of a page, if any.
+    pub fn get(&self, page: &Path) -> Option<&Entry> {
+        self.pages.get(page)
+    }
+
+    /// Records the state of a page.
+    pub fn insert(&mut self, page: &Path, entry: Entry) {
+        self.pages.insert(page.to_path_buf(), entry);
+    }
+}
+
+
+/// Parses a single line of the cache file.
+fn parse_entry(line: &str) -> Option<(PathBuf, Entry)> {
+    let mut fields = line.split('\t');
+    let page = PathBuf::from(fields.next()?);
+    let source = u64::from_str_radix(fields.next()?, 16).ok()?;
+    let output = u64::from_str_radix(fields.next()?, 16).ok()?;
+
+    let mut dependencies = Vec::new();
+    while let Some(d) = fields.next() {
+        let fingerprint = u64::from_str_radix(fields.next()?, 16).ok()?;
+        dependencies.push((Dependency::deserialize(d)?, fingerprint));
+    }
+
+    Some((page, Entry {
+        source,
+        output,
+        dependencies,
+    }))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_entry_reads_hashes_and_dependencies() {
+        let line = "blog/post.co\t00000000000000ff\t0000000000000010\tc:/site/_nav.co\t0000000000000001\te:/site/a.co\t0000000000000000\td:\t0000000000000002";
+        let (page, entry) = parse_entry(line).unwrap();
+        assert_eq!(page, PathBuf::from("blog/post.co"));
+        assert_eq!(entry.source, 0xff);
+        assert_eq!(entry.output, 0x10);
+        assert_eq!(entry.dependencies, vec![
+            (Dependency::Contents (PathBuf::from("/site/_nav.co")), 1),
+            (Dependency::Exists (PathBuf::from("/site/a.co")), 0),
+            (Dependency::Date, 2),
+        ]);
+    }
+
+    #[test]
+    fn parse_entry_rejects_malformed_lines() {
+        assert!(parse_entry("").is_none());
+        assert!(parse_entry("index.co\tzz\t00").is_none());
+        assert!(parse_entry("index.co\t01").is_none());
+        assert!(parse_entry("index.co\t01\t02\tc:/a.co").is_none());
+        assert!(parse_entry("index.co\t01\t02\tx:/a.co\t03").is_none());
+    }
+
+    #[test]
+    fn dependencies_round_trip() {
+        let dependencies = [
+            Dependency::Contents (PathBuf::from("a/b.co")),
+            Dependency::Exists (PathBuf::from("c.co")),
+            Dependency::Listing (PathBuf::from("data")),
+            Dependency::Date,
+        ];
+        for dependency in dependencies {
+            assert_eq!(Dependency::deserialize(&dependency.serialize()), Some(dependency));
+        }
+    }
+}
@@ -0,0 +1,308 @@
+//! Provides the command line interface of the `cobalt` executable.
+
+use std::path::PathBuf;
+
+use colored::control;
+
+use crate::{
+    error::{throw, Error},
+    log::{self, Verbosity},
+    backend::Format,
+};
+
+
+/// Enumerates the subcommands of the `cobalt` executable.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Command {
+    Build,
+    Check,
+    Clean,
+    New,
+    Init,
+    Render,
+    Ast,
+    Tokens,
+    Serve,
+    Watch,
+}
+
+impl Command {
+    /// Gets a subcommand from its name, if it exists.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "build" => Some(Self::Build),
+            "check" => Some(Self::Check),
+            "clean" => Some(Self::Clean),
+            "new" => Some(Self::New),
+            "init" => Some(Self::Init),
+            "render" => Some(Self::Render),
+            "ast" => Some(Self::Ast),
+            "tokens" => Some(Self::Tokens),
+            "serve" => Some(Self::Serve),
+            "watch" => Some(Self::Watch),
+            _ => None,
+        }
+    }
+
+    /// Gets the usage text of a subcommand.
+    pub fn usage(&self) -> &'static str {
+        match self {
+            Self::Build => "\
+Usage: cobalt build [OPTIONS]
+
+Compiles every page of the site and copies its assets into the output directory.
+Nothing in the output directory changes unless every page compiles.
+
+        --dry-run           Print the files that would be written and deleted without touching them",
+            Self::Check => "\
+Usage: cobalt check [OPTIONS]
+
+Compiles every page of the site without writing any files, reporting the first error.",
+            Self::Clean => "\
+Usage: cobalt clean [OPTIONS]
+
+Deletes the files produced by previous builds.
+
+        --dry-run           Print the files that would be deleted without touching them",
+            Self::New => "\
+Usage: cobalt new [OPTIONS] <DIRECTORY>
+
+Creates a new Cobalt site in the given directory, which must not exist or be empty.
+
+        --template <NAME>   Start from the 'basic' (default), 'blog' or 'docs' template",
+            Self::Init => "\
+Usage: cobalt init [OPTIONS]
+
+Creates a new Cobalt site in the current directory, keeping any existing files.
+
+        --template <NAME>   Start from the 'basic' (default), 'blog' or 'docs' template",
+            Self::Render => "\
+Usage: cobalt render [OPTIONS] <FILE>
+
+Renders a single page to standard output.  Use '-' to read from standard input.
+
+        --fragment          Render only the body of the page, without the surrounding HTML",
+            Self::Ast => "\
+Usage: cobalt ast [OPTIONS] <FILE>
+
+Prints the syntax tree of a single page as JSON.  Use '-' to read from standard input.",
+            Self::Tokens => "\
+Usage: cobalt tokens [OPTIONS] <FILE>
+
+Prints the tokens of a single page as JSON.  Use '-' to read from standard input.",
+            Self::Serve => "\
+Usage: cobalt serve [OPTIONS]
+
+Serves the site on localhost, rebuilding it when its sources change
+and reloading open pages in the browser.
+
+        --port <PORT>       Listen on the given port instead of 4000",
+            Self::Watch => "\
+Usage: cobalt watch [OPTIONS]
+
+Rebuilds the site whenever its sources change.",
+        }
+    }
+}
+
+
+/// Holds the general help text of the `cobalt` executable.
+const HELP: &str = "\
+Cobalt: a simple static web framework.
+
+Usage: cobalt [OPTIONS] [COMMAND] [ARGS]
+
+Commands:
+    build       Build the site (default)
+    check       Check the site for errors without writing any files
+    clean       Delete the files produced by previous builds
+    new         Create a new site
+    init        Create a new site in the current directory
+    render      Render a single page to standard output
+    ast         Print the syntax tree of a single page as JSON
+    tokens      Print the tokens of a single page as JSON
+    serve       Serve the site locally with live reload
+    watch       Rebuild the site whenever its sources change
+    help        Print this message or the help of a command";
+
+
+/// Holds the help text of the global options.
+const OPTIONS: &str = "\
+Options:
+    -c, --config <FILE>     Use the given configuration file instead of searching for 'cobalt.toml'
+    -o, --out <DIR>         Write output to the given directory instead of 'site.output'
+    -q, --quiet             Print errors only
+    -v, --verbose           Print every file that is written
+    -j, --jobs <N>          Compile up to N pages at once instead of one per CPU core
+        --format <FORMAT>   Write pages as 'html' (default) or 'markdown'
+        --color <WHEN>      Color output: 'auto', 'always' or 'never'
+    -h, --help              Print help";
+
+
+/// Holds the parsed command line.
+pub struct Cli {
+    pub command: Command,
+    pub args: Vec<String>,
+    pub config: Option<PathBuf>,
+    pub output: Option<PathBuf>,
+    pub template: Option<String>,
+    pub fragment: bool,
+    pub port: Option<u16>,
+    pub jobs: Option<usize>,
+    pub dry_run: bool,
+    pub format: Option<Format>,
+}
+
+impl Cli {
+    /// Parses the command line from an iterator over arguments, excluding the executable name.
+    /// 
+    /// Global options may appear before or after the subcommand.
+    pub fn parse<I: Iterator<Item = String>>(args: I) -> Self {
+        let mut command: Option<Command> = None;
+        let mut positional = Vec::new();
+        let mut config = None;
+        let mut output = None;
+        let mut template = None;
+        let mut fragment = false;
+        let mut port = None;
+        let mut jobs = None;
+        let mut dry_run = false;
+        let mut format = None;
+        let mut help = false;
+
+        let mut args = args.peekable();
+        while let Some(arg) = args.next() {
+            // Split `--flag=value` into its flag and value.
+            let (flag, inline) = match arg.split_once('=') {
+                Some((f, v)) if f.starts_with("--") => (f.to_string(), Some(v.to_string())),
+                _ => (arg.clone(), None),
+            };
+
+            let mut value = || match inline.clone().or_else(|| args.next()) {
+                Some(v) => v,
+                None => throw(Error::ExpectedArgument (flag.clone())),
+            };
+
+            match flag.as_str() {
+                "-c" | "--config" => config = Some(PathBuf::from(value())),
+                "-o" | "--out" => output = Some(PathBuf::from(value())),
+                "-q" | "--quiet" => log::set_verbosity(Verbosity::Quiet),
+                "-v" | "--verbose" => log::set_verbosity(Verbosity::Verbose),
+                "--color" => match value().as_str() {
+                    "always" => control::set_override(true),
+                    "never" => control::set_override(false),
+                    "auto" => control::unset_override(),
+                    w => throw(Error::InvalidArgument (format!("--color {}", w))),
+                },
+                "--template" => template = Some(value()),
+                "-j" | "--jobs" => match value().parse() {
+                    Ok(j) => jobs = Some(j),
+                    Err(_) => throw(Error::InvalidArgument (arg)),
+                },
+                "--fragment" => fragment = true,
+                "--dry-run" => dry_run = true,
+                "--format" => format = Some(Format::from_name(&value())),
+                "--port" => match value().parse() {
+                    Ok(p) => port = Some(p),
+                    Err(_) => throw(Error::InvalidArgument (arg)),
+                },
+                "-h" | "--help" => help = true,
+                "-" => positional.push(arg),
+                f if f.starts_with('-') => throw(Error::InvalidArgument (arg)),
+                _ => match command {
+                    None if arg == "help" => help = true,
+                    None => match Command::from_name(&arg) {
+                        Some(c) => command = Some(c),
+                        None => throw(Error::InvalidCommand (arg)),
+                    },
+                    Some(_) => positional.push(arg),
+                },
+            }
+        }
+
+        // Show the help of a command with `cobalt help <command>` or `cobalt <command> --help`.
+        if help {
+            let usage = match command {
+                Some(c) => c.usage(),
+                None => HELP,
+            };
+            println!("{}\n\n{}", usage, OPTIONS);
+            std::process::exit(0);
+        }
+
+        let command = command.unwrap_or(Command::Build);
+
+        Self {
+            command,
+            args: positional,
+            config,
+            output,
+            template,
+            fragment,
+            port,
+            jobs,
+            dry_run,
+            format,
+        }
+    }
+
+    /// Throws an error if any positional arguments were given to a command that takes none.
+    pub fn reject_args(&self) {
+        if let Some(a) = self.args.first() {
+            throw(Error::InvalidArgument (a.to_string()));
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::catch;
+
+    fn parse(args: &[&str]) -> Cli {
+        Cli::parse(args.iter().map(|a| a.to_string()))
+    }
+
+    #[test]
+    fn build_is_the_default_command() {
+        let cli = parse(&[]);
+        assert_eq!(cli.command, Command::Build);
+        assert!(cli.args.is_empty());
+        assert!(!cli.dry_run);
+    }
+
+    #[test]
+    fn options_may_surround_the_command() {
+        let cli = parse(&["-o", "public", "render", "--fragment", "page.co", "--config=site/cobalt.toml"]);
+        assert_eq!(cli.command, Command::Render);
+        assert_eq!(cli.args, vec!["page.co".to_string()]);
+        assert_eq!(cli.output, Some(PathBuf::from("public")));
+        assert_eq!(cli.config, Some(PathBuf::from("site/cobalt.toml")));
+        assert!(cli.fragment);
+    }
+
+    #[test]
+    fn values_are_parsed() {
+        let cli = parse(&["serve", "--port", "8080", "-j", "2", "--format", "markdown", "--dry-run"]);
+        assert_eq!(cli.command, Command::Serve);
+        assert_eq!(cli.port, Some(8080));
+        assert_eq!(cli.jobs, Some(2));
+        assert_eq!(cli.format, Some(Format::Markdown));
+        assert!(cli.dry_run);
+    }
+
+    #[test]
+    fn standard_input_is_a_positional_argument() {
+        let cli = parse(&["render", "-"]);
+        assert_eq!(cli.args, vec!["-".to_string()]);
+    }
+
+    #[test]
+    fn invalid_arguments_are_rejected() {
+        assert!(matches!(catch(|| parse(&["publish"])), Err(Error::InvalidCommand (c)) if c == "publish"));
+        assert!(matches!(catch(|| parse(&["--bogus"])), Err(Error::InvalidArgument (_))));
+        assert!(matches!(catch(|| parse(&["--port", "http"])), Err(Error::InvalidArgument (_))));
+        assert!(matches!(catch(|| parse(&["build", "--out"])), Err(Error::ExpectedArgument (f)) if f == "--out"));
+    }
+}
@@ -0,0 +1,224 @@
+//! Provides the website configuration read from `cobalt.toml`.
+
+use std::{
+    collections::HashMap,
+    env,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde_derive::Deserialize;
+
+use crate::{
+    error::{throw, Error},
+    files::hash,
+};
+
+/// Holds website configuration information.
+#[derive(Clone, Default, Deserialize)]
+pub struct Config {
+    pub site: Site,
+    pub style: Style,
+
+    // Holds optional user-defined control sequences, mapping each keyword to an HTML template,
+    // e.g. `badge = '<span class="badge {class}">{arg}</span>'`.
+    pub shortcodes: Option<HashMap<String, String>>,
+
+    // Holds optional variables available to every page through `\var{...}`,
+    // e.g. `company = "Hobbs Bros."`.
+    pub vars: Option<HashMap<String, toml::Value>>,
+
+    // Holds optional passes run on every page, in order, and their options.
+    pub passes: Option<Passes>,
+
+    // Holds optional external plugins, mapping control sequence keywords to executables,
+    // e.g. `chart = "plugins/chart.py"`.
+    pub plugins: Option<HashMap<String, Plugin>>,
+
+    // Holds the hash of the configuration file, used to invalidate the build cache.
+    #[serde(skip)]
+    pub hash: u64,
+}
+
+/// Holds general information about the website.
+#[derive(Clone, Default, Deserialize)]
+pub struct Site {
+    // Holds the website name.
+    pub name: String,
+
+    // Holds optional instructions on how to format the page title.
+    //
+    // Options are "page", "site", "page | site", and "site | page".
+    // If not specified, Cobalt defaults to "page".
+    pub title: Option<String>,
+
+    // Holds the Cobalt source directory, relative to `cobalt.toml`.
+    // 
+    // If not specified, Cobalt defaults to the directory containing `cobalt.toml`.
+    pub path: Option<String>,
+
+    // Holds the build output directory, relative to `cobalt.toml`.
+    //
+    // The source directory structure is mirrored into this directory.
+    // If not specified, Cobalt writes each page next to its source file.
+    pub output: Option<String>,
+
+    // Holds an optional directory of static assets, relative to `cobalt.toml`.
+    //
+    // Its contents are copied into the root of the output directory.
+    // If not specified, Cobalt uses the `static` directory if it exists.
+    #[serde(rename = "static")]
+    pub static_dir: Option<String>,
+
+    // Holds an optional directory of layouts, relative to `cobalt.toml`.
+    //
+    // If not specified, Cobalt uses the `layouts` directory.
+    pub layouts: Option<String>,
+
+    // Holds the optional name of the layout used by pages without `\layout{...}`,
+    // e.g. "default" for `layouts/default.html`.
+    //
+    // If not specified, Cobalt emits a minimal HTML shell.
+    pub layout: Option<String>,
+
+    // Holds an optional URL or path prefix under which the site is hosted,
+    // e.g. "https://example.com/docs" or "/docs".
+    //
+    // If specified, links beginning with `/` are prefixed by this URL.  If it is an absolute
+    // URL with a scheme, a `sitemap.xml` listing every page is also written.
+    // If not specified, links are made relative to each page.
+    pub base_url: Option<String>,
+
+    // Holds an optional switch for pretty URLs.
+    //
+    // If enabled, `blog/post.co` is written to `blog/post/index.html` and linked as `blog/post/`.
+    // If not specified, Cobalt defaults to `false` and writes `blog/post.html`.
+    pub pretty_urls: Option<bool>,
+
+    // Holds glob patterns of files that Cobalt should not copy or compile.
+    //
+    // Hidden files and directories are always skipped unless included below.
+    pub exclude: Option<Vec<String>>,
+
+    // Holds glob patterns of files that Cobalt should copy or compile even if
+    // they are hidden or excluded, e.g. ".well-known/**".
+    pub include: Option<Vec<String>>,
+}
+
+/// Holds the passes transforming every page, e.g.
+/// 
+/// ```toml
+/// [passes]
+/// run = ["strip-drafts", "number-headings"]
+/// 
+/// [passes.number-headings]
+/// from = 2
+/// ```
+#[derive(Clone, Default, Deserialize)]
+pub struct Passes {
+    // Holds the names of the passes to run, in order.
+    //
+    // Options are "number-headings", "strip-drafts" and "external-links".
+    pub run: Vec<String>,
+
+    // Holds the options of each pass, keyed by its name.
+    #[serde(flatten)]
+    pub options: HashMap<String, toml::Value>,
+}
+
+/// Holds an external plugin, given either as a command or as a table, e.g.
+/// 
+/// ```toml
+/// [plugins.chart]
+/// command = "plugins/chart.py"
+/// timeout = 30
+/// output = "cobalt"
+/// ```
+#[derive(Clone, Deserialize)]
+#[serde(untagged)]
+pub enum Plugin {
+    Command (String),
+    Options {
+        // Holds the executable, relative to `cobalt.toml` if it contains a `/`.
+        command: String,
+
+        // Holds the number of seconds the plugin may run.
+        //
+        // If not specified, Cobalt defaults to 10 seconds.
+        timeout: Option<u64>,
+
+        // Holds the kind of output of the plugin, "html" or "cobalt".
+        //
+        // If not specified, Cobalt defaults to "html".
+        output: Option<String>,
+    },
+}
+
+impl Plugin {
+    /// Gets the command running the plugin.
+    pub fn get_command(&self) -> &str {
+        match self {
+            Self::Command (c) => c,
+            Self::Options { command: c, .. } => c,
+        }
+    }
+
+    /// Gets the number of seconds the plugin may run, if specified.
+    pub fn get_timeout(&self) -> Option<u64> {
+        match self {
+            Self::Command (_) => None,
+            Self::Options { timeout: t, .. } => *t,
+        }
+    }
+
+    /// Gets the kind of output of the plugin.
+    pub fn get_output(&self) -> &str {
+        match self {
+            Self::Options { output: Some(o), .. } => o,
+            _ => "html",
+        }
+    }
+}
+
+/// Holds information about the website's CSS style.
+/// 
+/// Stylesheets are relative to the root of the site unless they have a scheme or begin with `//`.
+#[derive(Clone, Default, Deserialize)]
+pub struct Style {
+    pub default: String,
+    pub external: Option<Vec<String>>,
+}
+
+impl Config {
+    /// Searches the current and parent directories for `cobalt.toml`, if it exists.
+    pub fn search() -> Option<(Self, PathBuf)> {
+        let working_dir = match env::current_dir() {
+            Ok(p) => p,
+            Err(_) => throw(Error::CouldNotFindToml),
+        };
+
+        for path in working_dir.as_path().ancestors() {
+            let config_path = path.join("cobalt.toml");
+            if config_path.is_file() {
+                return Some((Self::load(&config_path), path.to_path_buf()));
+            }
+        }
+
+        None
+    }
+
+    /// Reads and parses the configuration file at the given path.
+    pub fn load(path: &Path) -> Self {
+        let file = match fs::read_to_string(path) {
+            Ok(f) => f,
+            Err(_) => throw(Error::CouldNotFindToml),
+        };
+        let mut config: Self = match toml::from_str(&file) {
+            Ok(t) => t,
+            Err(_) => throw(Error::CouldNotFindToml),
+        };
+        config.hash = hash(file.as_bytes());
+
+        config
+    }
+}
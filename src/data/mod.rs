@@ -0,0 +1,229 @@
+//! Provides the data tree, loaded from the TOML, JSON and CSV files of the `data` directory.
+//! 
+//! Each file becomes a value named after its path, e.g. `data/team.toml` is `data.team` and
+//! `data/releases/v1.json` is `data.releases.v1`.  A CSV file becomes an array of tables,
+//! one per row, keyed by the header row.
+
+use std::{
+    ffi::OsStr,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use toml::{value::Table, Value};
+
+use crate::{
+    error::{throw, Error},
+    ignore::{self, Rules},
+};
+
+
+/// Holds the data tree and the files it was loaded from.
+pub struct Data {
+    tree: Value,
+    files: Vec<PathBuf>,
+}
+
+impl Data {
+    /// Loads every data file within a directory, which may not exist.
+    pub fn load(directory: &Path) -> Self {
+        let mut tree = Table::new();
+        let mut files = Vec::new();
+
+        if !directory.is_dir() {
+            return Self {
+                tree: Value::Table (tree),
+                files,
+            };
+        }
+
+        for (path, relative) in ignore::walk(directory, &Rules::default(), |_| false) {
+            let value = match path.extension().and_then(OsStr::to_str) {
+                Some("toml") => parse_toml(&path),
+                Some("json") => parse_json(&path),
+                Some("csv") => parse_csv(&path),
+                _ => continue,
+            };
+
+            // Nest the value in tables named after the directories containing the file.
+            let mut table = &mut tree;
+            if let Some(parent) = relative.parent() {
+                for component in parent.components() {
+                    let name = component.as_os_str().to_string_lossy().to_string();
+                    let entry = table.entry(name).or_insert_with(|| Value::Table (Table::new()));
+                    table = match entry {
+                        Value::Table (t) => t,
+                        _ => throw(Error::InvalidData (path.display().to_string(), "conflicts with another data file".to_string())),
+                    };
+                }
+            }
+            if let Some(stem) = relative.file_stem() {
+                table.insert(stem.to_string_lossy().to_string(), value);
+            }
+
+            files.push(path);
+        }
+
+        Self {
+            tree: Value::Table (tree),
+            files,
+        }
+    }
+
+    /// Gets the data tree.
+    pub fn get_tree(&self) -> &Value {
+        &self.tree
+    }
+
+    /// Gets the data files that were loaded.
+    pub fn get_files(&self) -> &[PathBuf] {
+        &self.files
+    }
+}
+
+
+/// Reads a data file or throws an error naming it.
+fn read(path: &Path) -> String {
+    match fs::read_to_string(path) {
+        Ok(s) => s,
+        Err(e) => throw(Error::InvalidData (path.display().to_string(), e.to_string())),
+    }
+}
+
+
+/// Parses a TOML data file.
+fn parse_toml(path: &Path) -> Value {
+    match toml::from_str(&read(path)) {
+        Ok(v) => v,
+        Err(e) => throw(Error::InvalidData (path.display().to_string(), e.to_string())),
+    }
+}
+
+
+/// Parses a JSON data file, converting it into the equivalent TOML value.
+fn parse_json(path: &Path) -> Value {
+    match serde_json::from_str(&read(path)) {
+        Ok(v) => from_json(v),
+        Err(e) => throw(Error::InvalidData (path.display().to_string(), e.to_string())),
+    }
+}
+
+
+/// Converts a JSON value into a TOML value.  TOML has no null, so `null` becomes an empty string.
+fn from_json(value: serde_json::Value) -> Value {
+    match value {
+        serde_json::Value::Null => Value::String (String::new()),
+        serde_json::Value::Bool (b) => Value::Boolean (b),
+        serde_json::Value::Number (n) => match n.as_i64() {
+            Some(i) => Value::Integer (i),
+            None => Value::Float (n.as_f64().unwrap_or_default()),
+        },
+        serde_json::Value::String (s) => Value::String (s),
+        serde_json::Value::Array (a) => Value::Array (a.into_iter().map(from_json).collect()),
+        serde_json::Value::Object (o) => Value::Table (o.into_iter().map(|(k, v)| (k, from_json(v))).collect()),
+    }
+}
+
+
+/// Parses a CSV data file with a header row into an array of tables.
+fn parse_csv(path: &Path) -> Value {
+    let mut records = csv_records(&read(path)).into_iter();
+
+    let header = match records.next() {
+        Some(h) => h,
+        None => return Value::Array (Vec::new()),
+    };
+
+    let mut rows = Vec::new();
+    for record in records {
+        if record.len() != header.len() {
+            throw(Error::InvalidData (
+                path.display().to_string(),
+                format!("expected {} fields but got {}", header.len(), record.len()),
+            ));
+        }
+        let row = header.iter().cloned().zip(record.into_iter().map(Value::String)).collect();
+        rows.push(Value::Table (row));
+    }
+
+    Value::Array (rows)
+}
+
+
+/// Splits CSV text into records of fields, honoring double-quoted fields.
+fn csv_records(text: &str) -> Vec<Vec<String>> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            },
+            '"' => quoted = !quoted,
+            ',' if !quoted => record.push(std::mem::take(&mut field)),
+            '\r' if !quoted => (),
+            '\n' if !quoted => {
+                record.push(std::mem::take(&mut field));
+                records.push(std::mem::take(&mut record));
+            },
+            c => field.push(c),
+        }
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+
+    // Blank lines hold no record.
+    records.retain(|r| !(r.len() == 1 && r[0].is_empty()));
+
+    records
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(fields: &[&str]) -> Vec<String> {
+        fields.iter().map(|f| f.to_string()).collect()
+    }
+
+    #[test]
+    fn csv_records_split_lines_and_fields() {
+        let records = csv_records("name,role\nAda,Engineer\r\nGrace,Admiral");
+        assert_eq!(records, vec![
+            record(&["name", "role"]),
+            record(&["Ada", "Engineer"]),
+            record(&["Grace", "Admiral"]),
+        ]);
+    }
+
+    #[test]
+    fn csv_records_honor_quotes() {
+        let records = csv_records("\"Hobbs, Joseph\",\"says \"\"hi\"\"\"\n\"two\nlines\",x\n");
+        assert_eq!(records, vec![
+            record(&["Hobbs, Joseph", "says \"hi\""]),
+            record(&["two\nlines", "x"]),
+        ]);
+    }
+
+    #[test]
+    fn csv_records_skip_blank_lines_and_keep_empty_fields() {
+        let records = csv_records("a,b\n\n,\n");
+        assert_eq!(records, vec![record(&["a", "b"]), record(&["", ""])]);
+        assert!(csv_records("").is_empty());
+    }
+
+    #[test]
+    fn missing_directory_is_empty() {
+        let data = Data::load(Path::new("/nonexistent/cobalt/data"));
+        assert_eq!(data.get_tree(), &Value::Table (Table::new()));
+        assert!(data.get_files().is_empty());
+    }
+}
@@ -0,0 +1,14 @@
+//! Creates an interface for emitter hooks, which emit custom expressions.
+
+use crate::{
+    emitter::Emitter,
+    parser::Expression,
+};
+
+/// Emits the `Expression::Custom` nodes of a given name into HTML.
+pub trait Hook: Send + Sync {
+    /// Emits a custom expression from its value and children.
+    /// 
+    /// Children may be emitted with `Emitter::emit_fragment()`.
+    fn emit(&self, emitter: &Emitter, value: &str, children: Vec<Expression>) -> String;
+}
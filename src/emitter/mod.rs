@@ -1,13 +1,41 @@
 //! Provides a simple Cobalt-to-HTML emitter.
 
-use std::path::Path;
+pub mod hook;
+
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
 
 use crate::{
     parser::Expression,
     error::{throw, Error},
+    build::{self, Build},
+    cache::Dependency,
+    backend::Backend,
+    files::normalize,
+    include,
+    template,
     Config,
 };
 
+pub use hook::Hook;
+
+
+/// Holds the page shell used when a page has no layout.
+const DEFAULT_LAYOUT: &str = "\
+<!DOCTYPE html>\n\
+<html>\n\
+<head>\n\
+<title>{title}</title>\n\
+{head}</head>\n\
+<body>\n\
+{body}</body>\n\
+</html>\n\
+";
+
 
 /// Abstracts over the HTML processed in `Emitter::emit()`.
 pub struct Html {
@@ -17,6 +45,12 @@ pub struct Html {
     body: String,
 }
 
+impl Default for Html {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Html {
     /// Constructs a new instance of `Html`.
     pub fn new() -> Self {
@@ -58,32 +92,235 @@ impl Html {
         self.body.to_owned()
     }
 
-    /// Gets the site & page name based on the provided protocol.
-    pub fn get_name(&self, protocol: &str) -> String {
+    /// Gets the page name.
+    pub fn get_page(&self) -> String {
+        self.page.to_owned()
+    }
+
+    /// Gets the page title based on the provided protocol.
+    pub fn get_title(&self, protocol: &str) -> String {
         match protocol {
-            "page" => format!("<title>{}</title>\n", &self.page),
-            "site" => format!("<title>{}</title>\n", &self.site),
-            "page | site" => format!("<title>{} | {}</title>\n", &self.page, &self.site),
-            "site | page" => format!("<title>{} | {}</title>\n", &self.site, &self.page),
+            "page" => self.page.to_owned(),
+            "site" => self.site.to_owned(),
+            "page | site" => format!("{} | {}", &self.page, &self.site),
+            "site | page" => format!("{} | {}", &self.site, &self.page),
             _ => throw(Error::InvalidConfig (protocol.to_string())),
         }
     }
+
+    /// Gets the site & page name based on the provided protocol.
+    pub fn get_name(&self, protocol: &str) -> String {
+        format!("<title>{}</title>\n", self.get_title(protocol))
+    }
 }
 
 
 /// Provides the `emit()` method to transform Cobalt syntax trees into HTML.
-pub struct Emitter {
-    config: Config,
+pub struct Emitter<'a> {
+    config: &'a Config,
+    source: PathBuf,
+    page: PathBuf,
+    layouts: PathBuf,
+    shortcodes: PathBuf,
+    extension: String,
+    relative_root: String,
+    relative_source: String,
+    dependencies: RefCell<Vec<Dependency>>,
+    hooks: HashMap<String, &'a dyn Hook>,
+    build: Option<&'a Build>,
 }
 
-impl Emitter {
-    /// Constructs a new emitter from a parser.
-    pub fn new(config: Config) -> Self {
+impl<'a> Emitter<'a> {
+    /// Constructs a new emitter for a page.
+    /// 
+    /// `page` is the path of the page's source file relative to the `source` directory.
+    pub fn new(config: &'a Config, source: &Path, page: &Path) -> Self {
+        let pretty_urls = config.site.pretty_urls.unwrap_or(false);
+        let relative_root = build::relative_root(page, pretty_urls);
+
+        // With pretty URLs, a page is written one directory below its source file.
+        let relative_source = if pretty_urls && !build::is_index(page) {
+            "../".to_string()
+        } else {
+            String::new()
+        };
+
         Self {
             config,
+            source: source.to_path_buf(),
+            page: page.to_path_buf(),
+            layouts: source.join("layouts"),
+            shortcodes: source.join("shortcodes"),
+            extension: "html".to_string(),
+            relative_root,
+            relative_source,
+            dependencies: RefCell::new(Vec::new()),
+            hooks: HashMap::new(),
+            build: None,
+        }
+    }
+
+    /// Sets the directory in which layouts are looked up.
+    pub fn set_layouts(&mut self, layouts: &Path) {
+        self.layouts = layouts.to_path_buf();
+    }
+
+    /// Sets the directory in which shortcode templates are looked up.
+    pub fn set_shortcodes(&mut self, shortcodes: &Path) {
+        self.shortcodes = shortcodes.to_path_buf();
+    }
+
+    /// Sets the file extension of the pages that links point to, e.g. `md`.
+    pub fn set_extension(&mut self, extension: &str) {
+        self.extension = extension.to_string();
+    }
+
+    /// Sets the build the page belongs to, whose rules decide which source files are pages.
+    pub fn set_build(&mut self, build: &'a Build) {
+        self.build = Some(build);
+    }
+
+    /// Registers a hook emitting the custom expressions of the given name.
+    pub fn register(&mut self, name: &str, hook: &'a dyn Hook) {
+        self.hooks.insert(name.to_string(), hook);
+    }
+
+    /// Resolves a URL written in the source for use in the emitted page.
+    /// 
+    /// URLs beginning with `/` are relative to the root of the site and are prefixed
+    /// by `site.base_url` if specified, or made relative to the page otherwise.
+    /// Relative URLs remain relative to the page's source file, while fragments
+    /// and URLs with a scheme are left untouched.
+    pub fn url(&self, url: &str) -> String {
+        if url.starts_with("//") || url.starts_with('#') || url.starts_with('?') || has_scheme(url) {
+            return url.to_string();
+        }
+        if !url.starts_with('/') {
+            return format!("{}{}", &self.relative_source, url);
+        }
+
+        let path = url.trim_start_matches('/');
+        match &self.config.site.base_url {
+            Some(b) => format!("{}/{}", b.trim_end_matches('/'), path),
+            None if path.is_empty() && self.relative_root.is_empty() => "./".to_string(),
+            None => format!("{}{}", &self.relative_root, path),
+        }
+    }
+
+    /// Resolves a URL from the configuration, which is relative to the root of the site
+    /// unless it has a scheme or begins with `//`.
+    pub fn config_url(&self, url: &str) -> String {
+        if url.starts_with("//") || has_scheme(url) {
+            return url.to_string();
+        }
+        self.url(&format!("/{}", url.trim_start_matches('/')))
+    }
+
+    /// Records a file that the emitted page depends on.
+    pub fn depend(&self, dependency: Dependency) {
+        let mut dependencies = self.dependencies.borrow_mut();
+        if !dependencies.contains(&dependency) {
+            dependencies.push(dependency);
+        }
+    }
+
+    /// Gets the files that the pages emitted so far depend on.
+    pub fn dependencies(&self) -> Vec<Dependency> {
+        self.dependencies.borrow().to_owned()
+    }
+
+    /// Resolves a hyperlink, translating links to `.co` source files into links to their output.
+    /// 
+    /// Links to source files may be relative to the page or, beginning with `/`, to the root
+    /// of the site, and may carry a fragment or query, e.g. `/docs/about.co#team`.
+    /// Links to source files that are not compiled, because they are missing, partials or,
+    /// if the build is known, skipped by its rules, are broken.
+    pub fn link(&self, href: &str) -> String {
+        let split = href.find(['#', '?']).unwrap_or(href.len());
+        let (path, suffix) = href.split_at(split);
+
+        if !path.ends_with(".co") || has_scheme(path) || path.starts_with("//") {
+            return self.url(href);
+        }
+
+        let target = match path.strip_prefix('/') {
+            Some(p) => normalize(Path::new(p)),
+            None => match self.page.parent() {
+                Some(d) => normalize(&d.join(path)),
+                None => normalize(Path::new(path)),
+            },
+        };
+
+        if let Some(t) = &target {
+            self.depend(Dependency::Exists (self.source.join(t)));
+        }
+
+        let compiled = |t: &Path| match self.build {
+            Some(b) => b.is_page(t),
+            None => self.source.join(t).is_file() && !include::is_partial(t),
+        };
+
+        match target {
+            Some(t) if compiled(&t) => {
+                let pretty_urls = self.config.site.pretty_urls.unwrap_or(false);
+                self.url(&format!("{}{}", build::page_url(&t, pretty_urls, &self.extension), suffix))
+            },
+            _ => throw(Error::BrokenLink (href.to_string())),
+        }
+    }
+
+    /// Reads a layout from the layout directory by name, e.g. `post` for `layouts/post.html`.
+    fn layout(&self, name: &str) -> String {
+        let path = self.layouts.join(format!("{}.html", name));
+        self.depend(Dependency::Contents (path.clone()));
+
+        match fs::read_to_string(&path) {
+            Ok(s) => s,
+            Err(_) => throw(Error::CouldNotFindLayout (path.display().to_string())),
         }
     }
 
+    /// Gets the template of a user-defined control sequence, if any.
+    /// 
+    /// Templates in the `[shortcodes]` table take precedence over files in the shortcode
+    /// directory, e.g. `shortcodes/youtube.html` for `\youtube{...}`.
+    fn shortcode(&self, keyword: &str) -> Option<String> {
+        if let Some(t) = self.config.shortcodes.as_ref().and_then(|s| s.get(keyword)) {
+            return Some(t.to_owned());
+        }
+
+        let path = self.shortcodes.join(format!("{}.html", keyword));
+        self.depend(Dependency::Contents (path.clone()));
+        fs::read_to_string(path).ok()
+    }
+
+    /// Emits a user-defined control sequence by filling its template.
+    /// 
+    /// `{class}`, `{id}` and `{arg}` are replaced by the class, ID and whole argument.
+    /// The argument is also split on commas: `key=value` parts fill `{key}` and the
+    /// others fill `{0}`, `{1}`, etc. in order.
+    fn emit_shortcode(&self, template: &str, arg: &str, class: Option<String>, id: Option<String>) -> String {
+        let mut named = Vec::new();
+        let mut positional = Vec::new();
+
+        for part in arg.split(',').map(|p| p.trim()) {
+            match part.split_once('=') {
+                Some((k, v)) if is_keyword(k.trim()) => named.push((k.trim(), v.trim())),
+                _ => positional.push(part),
+            }
+        }
+
+        template::fill(template, |name| match name {
+            "class" => Some(class.to_owned().unwrap_or_default()),
+            "id" => Some(id.to_owned().unwrap_or_default()),
+            "arg" => Some(arg.to_string()),
+            _ => match name.parse::<usize>() {
+                Ok(i) => positional.get(i).map(|p| p.to_string()),
+                Err(_) => named.iter().find(|(k, _)| *k == name).map(|(_, v)| v.to_string()),
+            },
+        })
+    }
+
     /// Emits an expression, accounting for class and ID.
     fn emit_class_id(&self, name: &str, tag: &str, arg: &str, class: Option<String>, id: Option<String>) -> String {
         match class {
@@ -149,10 +386,12 @@ impl Emitter {
                 "pagename" => {
                     site = Some(a);
                 },
+                // Layouts are selected in `emit()` and have no output of their own.
+                "layout" => (),
                 "image" => body.push_str(&self.emit_class_id(
                     "img",
                     "src",
-                    &a,
+                    &self.url(&a),
                     c,
                     i,
                 )),
@@ -160,7 +399,7 @@ impl Emitter {
                     body.push_str(&self.emit_class_id(
                         "script",
                         "src",
-                        &a,
+                        &self.url(&a),
                         c,
                         i,
                     ));
@@ -170,7 +409,7 @@ impl Emitter {
                     body.push_str(&self.emit_class_id(
                         "a",
                         "href",
-                        &a,
+                        &self.url(&a),
                         Some("download".to_string()),
                         i,
                     ));
@@ -180,19 +419,22 @@ impl Emitter {
                     body.push_str(&self.emit_class_id(
                         "iframe",
                         "href",
-                        &a,
+                        &self.url(&a),
                         c,
                         i,
                     ));
                     body.push_str("</iframe>");
                 },
-                _ => throw(Error::InvalidCtrlSequence (k)),
+                _ => match self.shortcode(&k) {
+                    Some(t) => body.push_str(&self.emit_shortcode(&t, &a, c, i)),
+                    None => throw(Error::InvalidCtrlSequence (k)),
+                },
             },
             Expression::Paragraph (s) => body.push_str(&format!("<p>{}</p>", &s)),
             Expression::Hyperlink {
                 text: t,
                 href: h,
-            } => body.push_str(&format!("<a href={}>{}</a>", &h, &t)),
+            } => body.push_str(&format!("<a href=\"{}\">{}</a>", &self.link(&h), &t)),
             Expression::H1 (s) => body.push_str(&format!("<h1>{}</h1>\n", &s)),
             Expression::H2 (s) => body.push_str(&format!("<h2>{}</h2>\n", &s)),
             Expression::H3 (s) => body.push_str(&format!("<h3>{}</h3>\n", &s)),
@@ -208,21 +450,52 @@ impl Emitter {
 
                 body.push_str("</div>");
             },
+            Expression::Html (s) => body.push_str(&s),
+            Expression::Custom {
+                name: n,
+                value: v,
+                children: c,
+            } => match self.hooks.get(&n) {
+                Some(h) => body.push_str(&h.emit(self, &v, c)),
+                None => throw(Error::MissingHook (n)),
+            },
         };
 
         (site, head, body)
     }
 
+    /// Emits a vector of expressions into the body of a page only, without the page shell.
+    pub fn emit_fragment(&self, expressions: Vec<Expression>) -> String {
+        let mut body = String::new();
+
+        for expr in expressions {
+            body.push_str(&self.emit_expr(expr).2);
+        }
+
+        body
+    }
+
     /// Emits a vector of expressions into a `String`.
-    pub fn emit(&self, expressions: Vec<Expression>, root_directory: &Path) -> String {
-        let header = "\
-        <!DOCTYPE html>\n\
-        <html>\n\
-            <head>\n\
-        ".to_string();
-        let footer = "\
-        </html>\n\
-        ".to_string();
+    /// 
+    /// The page is wrapped in the layout selected by `\layout{...}` or `site.layout`, whose
+    /// placeholders `{title}`, `{head}`, `{body}`, `{root}`, `{site.name}`, `{page.name}`,
+    /// `{page.url}` and `{page.source}` are filled in.  `{head}` holds the stylesheets but not
+    /// the `<title>`, which layouts write with `{title}`.  Pages without a layout are wrapped
+    /// in a minimal HTML shell.
+    pub fn emit(&self, expressions: Vec<Expression>) -> String {
+        let layout = expressions
+            .iter()
+            .rev()
+            .find_map(|e| match e {
+                Expression::Ctrl { keyword: k, argument: a, .. } if k == "layout" => Some(a.to_owned()),
+                _ => None,
+            })
+            .or_else(|| self.config.site.layout.to_owned());
+        let layout = match layout {
+            Some(l) => self.layout(&l),
+            None => DEFAULT_LAYOUT.to_string(),
+        };
+
         let mut html = Html::new();
 
         html.set_site(self.config.site.name.to_owned());
@@ -238,22 +511,16 @@ impl Emitter {
             }
         }
 
-        // Push configuration data to the head
+        // The title is left to the layout, through `{title}`.
         let title_protocol = match &self.config.site.title {
             Some(s) => s,
             None => "page",
         };
-        html.push_head(&html.get_name(title_protocol));
 
         // Emit primary stylesheet and external stylesheets.
-        let stylesheet_path = root_directory.join(self.config.style.default.to_owned());
-        let stylesheet = match stylesheet_path.into_os_string().into_string() {
-            Ok(s) => s,
-            Err(_) => throw(Error::CouldNotOpenFile (self.config.style.default.to_owned())),
-        };
         let stylesheet_link = format!(
             "<link rel=\"stylesheet\" href=\"{}\">",
-            &stylesheet,
+            &self.config_url(&self.config.style.default),
         );
         html.push_head(&stylesheet_link);
 
@@ -262,28 +529,144 @@ impl Emitter {
             for stylesheet in s {
                 let stylesheet_link = format!(
                     "<link rel=\"stylesheet\" href=\"{}\">",
-                    &stylesheet,
+                    &self.config_url(stylesheet),
                 );
                 stylesheets.push_str(&stylesheet_link);
             }
             html.push_head(&stylesheets);
         }
 
-        html.push_head("\
-        </head>\n\
-        <body>\n\
-        ");
+        let pretty_urls = self.config.site.pretty_urls.unwrap_or(false);
+
+        template::fill(&layout, |name| match name {
+            "title" => Some(html.get_title(title_protocol)),
+            "head" => Some(html.get_head()),
+            "body" => Some(html.get_body()),
+            "root" => Some(self.url("/")),
+            "site.name" => Some(self.config.site.name.to_owned()),
+            "page.name" => Some(html.get_page()),
+            "page.url" => Some(self.url(&build::page_url(&self.page, pretty_urls, &self.extension))),
+            "page.source" => Some(self.page.display().to_string()),
+            _ => None,
+        })
+    }
+}
 
-        html.push_body("\
-        </body>\n\
-        ");
 
-        let mut output = String::new();
-        output.push_str(&header);
-        output.push_str(&html.get_head());
-        output.push_str(&html.get_body());
-        output.push_str(&footer);
+impl Backend for Emitter<'_> {
+    fn emit(&self, expressions: Vec<Expression>) -> String {
+        Emitter::emit(self, expressions)
+    }
 
-        output
+    fn emit_fragment(&self, expressions: Vec<Expression>) -> String {
+        Emitter::emit_fragment(self, expressions)
     }
-}
\ No newline at end of file
+
+    fn dependencies(&self) -> Vec<Dependency> {
+        Emitter::dependencies(self)
+    }
+}
+
+
+/// Checks whether a string is a valid shortcode argument name, e.g. `color`.
+fn is_keyword(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '-')
+}
+
+
+/// Checks whether a URL begins with a scheme such as `https:` or `mailto:`.
+pub fn has_scheme(url: &str) -> bool {
+    match url.find(':') {
+        Some(i) => !url[..i].is_empty() && !url[..i].contains(['/', '?', '#']),
+        None => false,
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        error::catch,
+        files::write,
+    };
+
+    /// Creates a source directory holding the given, empty source files.
+    fn source(name: &str, files: &[&str]) -> PathBuf {
+        let source = std::env::temp_dir().join(format!("cobalt-emitter-{}-{}", name, std::process::id()));
+        for file in files {
+            write(&source.join(file), String::new());
+        }
+        source
+    }
+
+    fn config(base_url: Option<&str>, pretty_urls: bool) -> Config {
+        let mut config = Config::default();
+        config.site.base_url = base_url.map(|b| b.to_string());
+        config.site.pretty_urls = Some(pretty_urls);
+        config
+    }
+
+    #[test]
+    fn url_is_relative_to_the_page() {
+        let config = config(None, false);
+        let emitter = Emitter::new(&config, Path::new("/site"), Path::new("blog/post.co"));
+        assert_eq!(emitter.url("/img/a.png"), "../img/a.png");
+        assert_eq!(emitter.url("img/a.png"), "img/a.png");
+        assert_eq!(emitter.url("https://example.com/"), "https://example.com/");
+        assert_eq!(emitter.url("#top"), "#top");
+
+        let emitter = Emitter::new(&config, Path::new("/site"), Path::new("index.co"));
+        assert_eq!(emitter.url("/"), "./");
+    }
+
+    #[test]
+    fn url_with_base_url_and_pretty_urls() {
+        let config = config(Some("https://example.com/docs/"), true);
+        let emitter = Emitter::new(&config, Path::new("/site"), Path::new("blog/post.co"));
+        assert_eq!(emitter.url("/img/a.png"), "https://example.com/docs/img/a.png");
+        assert_eq!(emitter.url("img/a.png"), "../img/a.png");
+    }
+
+    #[test]
+    fn config_url_is_relative_to_the_site_root() {
+        let config = config(None, false);
+        let emitter = Emitter::new(&config, Path::new("/site"), Path::new("blog/post.co"));
+        assert_eq!(emitter.config_url("css/x.css"), "../css/x.css");
+        assert_eq!(emitter.config_url("/css/x.css"), "../css/x.css");
+        assert_eq!(emitter.config_url("https://cdn.example.com/x.css"), "https://cdn.example.com/x.css");
+        assert_eq!(emitter.config_url("//cdn.example.com/x.css"), "//cdn.example.com/x.css");
+    }
+
+    #[test]
+    fn link_translates_source_files() {
+        let source = source("link", &["index.co", "blog/post.co", "blog/other.co"]);
+
+        let plain = config(None, false);
+        let emitter = Emitter::new(&plain, &source, Path::new("blog/post.co"));
+        assert_eq!(emitter.link("other.co#top"), "../blog/other.html#top");
+        assert_eq!(emitter.link("/index.co"), "../index.html");
+        assert_eq!(emitter.link("notes.txt"), "notes.txt");
+        assert_eq!(emitter.dependencies().len(), 2);
+
+        let pretty = config(None, true);
+        let emitter = Emitter::new(&pretty, &source, Path::new("blog/post.co"));
+        assert_eq!(emitter.link("other.co"), "../../blog/other/");
+
+        let _ = fs::remove_dir_all(&source);
+    }
+
+    #[test]
+    fn link_to_a_missing_source_file_is_broken() {
+        let source = source("broken", &["index.co", "_nav.co"]);
+
+        let config = config(None, false);
+        let emitter = Emitter::new(&config, &source, Path::new("index.co"));
+        let broken = catch(|| emitter.link("/posts/missing.co"));
+        assert!(matches!(broken, Err(Error::BrokenLink (l)) if l == "/posts/missing.co"));
+        assert!(matches!(catch(|| emitter.link("../outside.co")), Err(Error::BrokenLink (_))));
+        assert!(matches!(catch(|| emitter.link("_nav.co")), Err(Error::BrokenLink (_))));
+
+        let _ = fs::remove_dir_all(&source);
+    }
+}
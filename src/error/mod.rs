@@ -1,15 +1,22 @@
 //! Provides an error handler for the Cobalt framework.
 
-use std::process::exit;
+use std::{
+    cell::Cell,
+    panic::{self, AssertUnwindSafe},
+    process::exit,
+};
 
 use colored::*;
 
 /// Enumerates the types of errors thrown by the Cobalt compiler.
+#[derive(Clone, Debug)]
 pub enum Error {
     CouldNotReadFile,
     CouldNotFindToml,
     CouldNotWriteFile (String),
     CouldNotOpenFile (String),
+    CouldNotCreateDirectory (String),
+    CouldNotDeleteFile (String),
     InvalidConfig (String),
     InvalidCtrlSequence (String),
     TooManyHeadings,
@@ -18,28 +25,117 @@ pub enum Error {
     CouldNotParse (String),
     ExpectedHeading (String),
     ExpectedParen (String),
+    InvalidArgument (String),
+    ExpectedArgument (String),
+    InvalidCommand (String),
+    InvalidTemplate (String),
+    DirectoryNotEmpty (String),
+    AlreadyInitialized (String),
+    BrokenLink (String),
+    CouldNotBind (u16),
+    InPage (String, Box<Error>),
+    CouldNotFindLayout (String),
+    CouldNotFindInclude (String),
+    IncludeCycle (String),
+    UndefinedVariable (String),
+    InvalidFrontMatter (String),
+    InvalidData (String, String),
+    UnmatchedBlock (String),
+    NotAList (String),
+    MissingHook (String),
+    UnknownPass (String),
+    PluginFailed (String, String),
+    InvalidFormat (String),
+    InvalidJson (String),
+    UnsupportedVersion (u32),
 }
 
 use Error::*;
 
+thread_local! {
+    // Holds whether errors thrown on this thread are caught by `catch()`.
+    static CATCHING: Cell<bool> = const { Cell::new(false) };
+}
+
+impl Error {
+    /// Gets a human-readable description of the error.
+    pub fn message(&self) -> String {
+        match self {
+            CouldNotReadFile => "Could not read input file".to_string(),
+            CouldNotFindToml => "Could not find or parse configuration file 'cobalt.toml'".to_string(),
+            CouldNotWriteFile (s) => format!("Could not write to file {}", s),
+            CouldNotOpenFile (s) => format!("Could not open file {}", s),
+            CouldNotCreateDirectory (s) => format!("Could not create directory {}", s),
+            CouldNotDeleteFile (s) => format!("Could not delete file {}", s),
+            InvalidConfig (s) => format!("Invalid configuration sequence: {}", s),
+            InvalidCtrlSequence (s) => format!("Invalid control sequence: {}", s),
+            TooManyHeadings => "Too many heading symbols '#'".to_string(),
+            UnexpectedEof => "Unexpected end of file when parsing".to_string(),
+            ExpectedOpen (s) => format!("Expected opening brace '{{', bracket '[', or parenthesis '(', but got {}", s),
+            CouldNotParse (s) => format!("Could not parse near token {}", s),
+            ExpectedHeading (s) => format!("Expected heading, got token {}", s),
+            ExpectedParen (s) => format!("Expected opening parenthesis '(' but got {}", s),
+            InvalidArgument (s) => format!("Invalid command line argument: {}", s),
+            ExpectedArgument (s) => format!("Expected a value after command line argument {}", s),
+            InvalidCommand (s) => format!("Invalid command '{}', run 'cobalt help' for a list of commands", s),
+            InvalidTemplate (s) => format!("Invalid template '{}', expected 'basic', 'blog' or 'docs'", s),
+            DirectoryNotEmpty (s) => format!("Directory {} already exists and is not empty", s),
+            AlreadyInitialized (s) => format!("Directory {} already holds a 'cobalt.toml'", s),
+            BrokenLink (s) => format!("Link to {} does not point to a source file", s),
+            CouldNotBind (p) => format!("Could not listen on port {}", p),
+            InPage (p, e) => format!("{} (in {})", e.message(), p),
+            CouldNotFindLayout (s) => format!("Could not find layout {}", s),
+            CouldNotFindInclude (s) => format!("Could not find included file {}", s),
+            IncludeCycle (s) => format!("Include cycle: {}", s),
+            UndefinedVariable (s) => format!("Undefined variable '{}'", s),
+            InvalidFrontMatter (s) => format!("Invalid front matter: {}", s),
+            InvalidData (f, s) => format!("Invalid data file {}: {}", f, s),
+            UnmatchedBlock (s) => format!("Unmatched block '\\{}'", s),
+            NotAList (s) => format!("Variable '{}' is not a list or table", s),
+            MissingHook (s) => format!("No emitter hook registered for custom expression '{}'", s),
+            PluginFailed (k, s) => format!("Plugin '{}' failed: {}", k, s),
+            InvalidFormat (s) => format!("Invalid format '{}', expected 'html' or 'markdown'", s),
+            InvalidJson (s) => format!("Invalid JSON: {}", s),
+            UnsupportedVersion (v) => format!("Unsupported JSON version {}, expected {}", v, crate::ast::VERSION),
+            UnknownPass (s) => format!("Unknown pass '{}', expected 'number-headings', 'strip-drafts' or 'external-links'", s),
+        }
+    }
+}
+
+
 /// Throws errors and halts the program.
+/// 
+/// Within `catch()`, the error is instead returned from `catch()`.
 pub fn throw(error: Error) -> ! {
-    let msg = match error {
-        CouldNotReadFile => format!("Could not read input file"),
-        CouldNotFindToml => format!("Could not find or parse configuration file 'cobalt.toml'"),
-        CouldNotWriteFile (s) => format!("Could not write to file {}", s),
-        CouldNotOpenFile (s) => format!("Could not open file {}", s),
-        InvalidConfig (s) => format!("Invalid configuration sequence: {}", s),
-        InvalidCtrlSequence (s) => format!("Invalid control sequence: {}", s),
-        TooManyHeadings => format!("Too many heading symbols '#'"),
-        UnexpectedEof => format!("Unexpected end of file when parsing"),
-        ExpectedOpen (s) => format!("Expected opening brace '{{', bracket '[', or parenthesis '(', but got {}", s),
-        CouldNotParse (s) => format!("Could not parse near token {}", s),
-        ExpectedHeading (s) => format!("Expected heading, got token {}", s),
-        ExpectedParen (s) => format!("Expected opening parenthesis '(' but got {}", s),
-    };
-
-    println!("{}: {}\nCompiler exiting.", "error".red().bold(), msg);
-
-    exit(0);
+    if CATCHING.with(|c| c.get()) {
+        panic::resume_unwind(Box::new(error));
+    }
+
+    eprintln!("{}: {}\nCompiler exiting.", "error".red().bold(), error.message());
+
+    exit(1);
+}
+
+
+/// Prints an error without halting the program.
+pub fn report(error: &Error) {
+    eprintln!("{}: {}", "error".red().bold(), error.message());
+}
+
+
+/// Runs a closure, returning any error it throws instead of halting the program.
+/// 
+/// This allows long-running commands such as `cobalt watch` to survive failed builds.
+pub fn catch<T, F: FnOnce() -> T>(f: F) -> Result<T, Error> {
+    let previous = CATCHING.with(|c| c.replace(true));
+    let result = panic::catch_unwind(AssertUnwindSafe(f));
+    CATCHING.with(|c| c.set(previous));
+
+    match result {
+        Ok(t) => Ok(t),
+        Err(e) => match e.downcast::<Error>() {
+            Ok(e) => Err(*e),
+            Err(e) => panic::resume_unwind(e),
+        },
+    }
 }
\ No newline at end of file
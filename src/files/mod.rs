@@ -0,0 +1,161 @@
+//! Provides file system helpers used throughout the Cobalt framework.
+
+use std::{
+    env,
+    fs::{
+        self,
+        OpenOptions,
+    },
+    io::Write,
+    path::{Component, Path, PathBuf},
+};
+
+use crate::error::{throw, Error};
+
+
+/// Reads a file to a `String` or throws an error if impossible.
+pub fn read(filename: &Path) -> String {
+    match fs::read_to_string(filename) {
+        Ok(f) => f,
+        Err(_) => throw(Error::CouldNotReadFile),
+    }
+}
+
+
+/// Writes a file from a `String` or throws an error if impossible.
+/// 
+/// Any missing parent directories are created.
+pub fn write(filename: &Path, file: String) {
+    if let Some(parent) = filename.parent() {
+        create_dir(parent);
+    }
+
+    let mut output = match OpenOptions::new()
+        .create(true)
+        .write(true)
+        .truncate(true)
+        .open(filename) {
+            Ok(f) => f,
+            Err(_) => throw(Error::CouldNotOpenFile (filename.display().to_string())),
+    };
+    match output.write_all(file.as_bytes()) {
+        Ok(_) => (),
+        Err(_) => throw(Error::CouldNotWriteFile (filename.display().to_string())),
+    };
+}
+
+
+/// Creates a directory and all of its parents or throws an error if impossible.
+pub fn create_dir(directory: &Path) {
+    if directory.as_os_str().is_empty() || directory.is_dir() {
+        return;
+    }
+    if fs::create_dir_all(directory).is_err() {
+        throw(Error::CouldNotCreateDirectory (directory.display().to_string()));
+    }
+}
+
+
+/// Hashes a slice of bytes using the 64-bit FNV-1a algorithm.
+/// 
+/// Unlike the standard library's hasher, the result is stable across
+/// platforms and compiler versions.
+pub fn hash(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in bytes {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+
+/// Hashes the contents of a file, if it can be read.
+pub fn hash_file(filename: &Path) -> Option<u64> {
+    fs::read(filename).ok().map(|c| hash(&c))
+}
+
+
+/// Checks whether a destination file already holds the same contents as a source file.
+/// 
+/// Files with equal sizes and modification times are assumed to be unchanged;
+/// files with equal sizes but different modification times are compared by hash.
+pub fn unchanged(source: &Path, destination: &Path) -> bool {
+    let (src_meta, dst_meta) = match (fs::metadata(source), fs::metadata(destination)) {
+        (Ok(s), Ok(d)) => (s, d),
+        _ => return false,
+    };
+
+    if dst_meta.len() != src_meta.len() {
+        return false;
+    }
+    let src_time = src_meta.modified().ok();
+    if src_time.is_some() && src_time == dst_meta.modified().ok() {
+        return true;
+    }
+
+    hash_file(source).is_some() && hash_file(source) == hash_file(destination)
+}
+
+
+/// Copies a file, preserving its modification time, or throws an error if impossible.
+pub fn copy(source: &Path, destination: &Path) {
+    let src_meta = match fs::metadata(source) {
+        Ok(m) => m,
+        Err(_) => throw(Error::CouldNotReadFile),
+    };
+
+    if let Some(parent) = destination.parent() {
+        create_dir(parent);
+    }
+    if fs::copy(source, destination).is_err() {
+        throw(Error::CouldNotWriteFile (destination.display().to_string()));
+    }
+    set_modified(destination, &src_meta);
+}
+
+
+/// Sets a file's modification time to match the given metadata, if possible.
+fn set_modified(filename: &Path, metadata: &fs::Metadata) {
+    if let Ok(time) = metadata.modified() {
+        if let Ok(f) = OpenOptions::new().write(true).open(filename) {
+            let _ = f.set_modified(time);
+        }
+    }
+}
+
+
+/// Makes a path absolute against the current working directory, without resolving symbolic links.
+pub fn absolute(path: &Path) -> PathBuf {
+    if path.is_absolute() {
+        return path.to_path_buf();
+    }
+
+    match env::current_dir() {
+        Ok(d) => d.join(path),
+        Err(_) => path.to_path_buf(),
+    }
+}
+
+
+/// Lexically normalizes a relative path, resolving `.` and `..` components.
+/// 
+/// Returns `None` if the path escapes its root.
+pub fn normalize(path: &Path) -> Option<PathBuf> {
+    let mut normalized = PathBuf::new();
+
+    for component in path.components() {
+        match component {
+            Component::Normal(c) => normalized.push(c),
+            Component::CurDir => (),
+            Component::ParentDir => {
+                if !normalized.pop() {
+                    return None;
+                }
+            },
+            _ => return None,
+        }
+    }
+
+    Some(normalized)
+}
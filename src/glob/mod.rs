@@ -0,0 +1,196 @@
+//! Provides simple glob patterns for matching paths within a site.
+
+use std::path::Path;
+
+
+/// Holds a glob pattern such as `*.bak`, `drafts/**` or `img/?.png`.
+/// 
+/// Patterns without a `/` match the name of any file or directory,
+/// while patterns containing a `/` match the whole path relative to the site.
+/// `*` and `?` never match `/`, but `**` matches any number of directories.
+#[derive(Clone, Debug)]
+pub struct Pattern {
+    segments: Vec<String>,
+    anchored: bool,
+}
+
+impl Pattern {
+    /// Constructs a new `Pattern` from a `&str`.
+    pub fn new(pattern: &str) -> Self {
+        let trimmed = pattern.trim().trim_end_matches('/');
+        let anchored = trimmed.contains('/');
+        let segments = trimmed
+            .trim_start_matches('/')
+            .split('/')
+            .filter(|s| !s.is_empty())
+            .map(|s| s.to_string())
+            .collect();
+
+        Self {
+            segments,
+            anchored,
+        }
+    }
+
+    /// Constructs a list of patterns from a list of strings.
+    pub fn list(patterns: &[String]) -> Vec<Self> {
+        patterns.iter().map(|p| Self::new(p)).collect()
+    }
+
+    /// Checks whether a relative path matches this pattern.
+    pub fn matches(&self, path: &Path) -> bool {
+        let components: Vec<String> = path
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy().to_string())
+            .collect();
+
+        if self.anchored {
+            match_segments(&self.segments, &components)
+        } else {
+            components.iter().any(|c| match self.segments.first() {
+                Some(s) => match_segment(s, c),
+                None => false,
+            })
+        }
+    }
+
+    /// Checks whether this pattern may match a path within the given directory.
+    pub fn may_match_within(&self, directory: &Path) -> bool {
+        if !self.anchored {
+            return true;
+        }
+
+        let components: Vec<String> = directory
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy().to_string())
+            .collect();
+
+        match_prefix(&self.segments, &components)
+    }
+}
+
+
+/// Checks whether any of the given patterns matches a relative path.
+pub fn matches_any(patterns: &[Pattern], path: &Path) -> bool {
+    patterns.iter().any(|p| p.matches(path))
+}
+
+
+/// Matches pattern segments against path components, expanding `**`.
+/// 
+/// A path also matches if a prefix of it matches, so that a pattern
+/// naming a directory applies to everything inside that directory.
+fn match_segments(segments: &[String], components: &[String]) -> bool {
+    match segments.first() {
+        None => true,
+        Some(s) if s == "**" => {
+            (0..=components.len()).any(|i| match_segments(&segments[1..], &components[i..]))
+        },
+        Some(s) => match components.first() {
+            Some(c) => match_segment(s, c) && match_segments(&segments[1..], &components[1..]),
+            None => false,
+        },
+    }
+}
+
+
+/// Checks whether path components could be the start of a path matched by pattern segments.
+fn match_prefix(segments: &[String], components: &[String]) -> bool {
+    match (segments.first(), components.first()) {
+        (_, None) => true,
+        (None, Some(_)) => true,
+        (Some(s), Some(_)) if s == "**" => true,
+        (Some(s), Some(c)) => match_segment(s, c) && match_prefix(&segments[1..], &components[1..]),
+    }
+}
+
+
+/// Matches a single pattern segment containing `*` and `?` against a name.
+fn match_segment(segment: &str, name: &str) -> bool {
+    let pattern: Vec<char> = segment.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+
+    // Tracks the position to backtrack to after the last `*`.
+    let (mut p, mut n) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+
+    while n < name.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == name[n]) {
+            p += 1;
+            n += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, n));
+            p += 1;
+        } else if let Some((sp, sn)) = star {
+            p = sp + 1;
+            n = sn + 1;
+            star = Some((sp, sn + 1));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn segments(path: &str) -> Vec<String> {
+        path.split('/').map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn match_segment_literal() {
+        assert!(match_segment("index.co", "index.co"));
+        assert!(!match_segment("index.co", "index.cob"));
+        assert!(!match_segment("index.co", "ndex.co"));
+    }
+
+    #[test]
+    fn match_segment_wildcards() {
+        assert!(match_segment("*.bak", "notes.bak"));
+        assert!(match_segment("*.bak", ".bak"));
+        assert!(!match_segment("*.bak", "notes.bak.co"));
+        assert!(match_segment("a*b*c", "aXXbYYbc"));
+        assert!(match_segment("?.png", "a.png"));
+        assert!(!match_segment("?.png", "ab.png"));
+        assert!(match_segment("*", ""));
+        assert!(match_segment("**", "anything"));
+    }
+
+    #[test]
+    fn match_segments_prefix_applies_to_contents() {
+        assert!(match_segments(&segments("drafts"), &segments("drafts/post.co")));
+        assert!(!match_segments(&segments("drafts/post.co"), &segments("drafts")));
+        assert!(!match_segments(&segments("img/*.png"), &segments("img/logo.svg")));
+    }
+
+    #[test]
+    fn match_segments_double_star() {
+        let pattern = segments("docs/**/*.md");
+        assert!(match_segments(&pattern, &segments("docs/readme.md")));
+        assert!(match_segments(&pattern, &segments("docs/a/b/readme.md")));
+        assert!(!match_segments(&pattern, &segments("blog/readme.md")));
+        assert!(match_segments(&segments("**"), &[]));
+    }
+
+    #[test]
+    fn unanchored_pattern_matches_any_component() {
+        let pattern = Pattern::new("*.bak");
+        assert!(pattern.matches(Path::new("a/b/c.bak")));
+        assert!(!pattern.matches(Path::new("a/b/c.co")));
+        assert!(pattern.may_match_within(Path::new("anywhere")));
+    }
+
+    #[test]
+    fn anchored_pattern_matches_from_the_root() {
+        let pattern = Pattern::new("/drafts/*.co");
+        assert!(pattern.matches(Path::new("drafts/post.co")));
+        assert!(!pattern.matches(Path::new("blog/drafts/post.co")));
+        assert!(pattern.may_match_within(Path::new("drafts")));
+        assert!(!pattern.may_match_within(Path::new("blog")));
+    }
+}
@@ -0,0 +1,137 @@
+//! Provides the rules deciding which files Cobalt compiles, copies and watches.
+//! 
+//! Hidden files and directories are skipped by default, as are files matching
+//! `site.exclude` or a line of `.cobaltignore`.  Files matching `site.include`,
+//! or a line of `.cobaltignore` beginning with `!`, are never skipped.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use walkdir::WalkDir;
+
+use crate::{
+    Config,
+    error::{throw, Error},
+    glob::{self, Pattern},
+    log,
+};
+
+
+/// Holds the name of the ignore file, next to `cobalt.toml`.
+pub const IGNORE_FILE: &str = ".cobaltignore";
+
+
+/// Holds the patterns of files to skip and files to keep regardless.
+#[derive(Default)]
+pub struct Rules {
+    exclude: Vec<Pattern>,
+    include: Vec<Pattern>,
+}
+
+impl Rules {
+    /// Constructs the rules of a site from its configuration and its `.cobaltignore`, if any.
+    pub fn new(config: &Config, root: &Path) -> Self {
+        let mut exclude = match &config.site.exclude {
+            Some(e) => Pattern::list(e),
+            None => Vec::new(),
+        };
+        let mut include = match &config.site.include {
+            Some(i) => Pattern::list(i),
+            None => Vec::new(),
+        };
+
+        if let Ok(file) = fs::read_to_string(root.join(IGNORE_FILE)) {
+            for line in file.lines().map(|l| l.trim()) {
+                if line.is_empty() || line.starts_with('#') {
+                    continue;
+                }
+                match line.strip_prefix('!') {
+                    Some(l) => include.push(Pattern::new(l)),
+                    None => exclude.push(Pattern::new(line)),
+                }
+            }
+        }
+
+        Self {
+            exclude,
+            include,
+        }
+    }
+
+    /// Checks whether a file or directory, relative to the walked directory, is skipped.
+    pub fn is_excluded(&self, relative: &Path) -> bool {
+        if glob::matches_any(&self.include, relative) {
+            return false;
+        }
+
+        is_hidden(relative) || glob::matches_any(&self.exclude, relative)
+    }
+
+    /// Checks whether a skipped directory may still contain files that are included.
+    pub fn may_include_within(&self, relative: &Path) -> bool {
+        self.include.iter().any(|p| p.may_match_within(relative))
+    }
+}
+
+
+/// Checks whether any component of a relative path is hidden, e.g. `.git`.
+fn is_hidden(relative: &Path) -> bool {
+    relative
+        .components()
+        .any(|c| c.as_os_str().to_string_lossy().starts_with('.'))
+}
+
+
+/// Walks a directory and returns its files with their paths relative to the directory.
+/// 
+/// Files skipped by the rules and reserved directories are left out.  Symbolic links
+/// are followed, but links that loop back to one of their ancestors are skipped.
+pub fn walk<F>(directory: &Path, rules: &Rules, reserved: F) -> Vec<(PathBuf, PathBuf)>
+where
+    F: Fn(&Path) -> bool,
+{
+    let mut files = Vec::new();
+
+    let walker = WalkDir::new(directory)
+        .follow_links(true)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|e| {
+            if e.depth() == 0 {
+                return true;
+            }
+            if reserved(e.path()) {
+                return false;
+            }
+
+            // Directories are pruned unless an included file may lie within them.
+            let relative = e.path().strip_prefix(directory).unwrap_or(e.path());
+            !rules.is_excluded(relative)
+                || (e.file_type().is_dir() && rules.may_include_within(relative))
+        });
+
+    for entry in walker {
+        let entry = match entry {
+            Ok(e) => e,
+            Err(e) if e.loop_ancestor().is_some() => {
+                if let Some(p) = e.path() {
+                    log::warn(&format!("Skipping symbolic link loop at {}", p.display()));
+                }
+                continue;
+            },
+            Err(_) => throw(Error::CouldNotReadFile),
+        };
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        if let Ok(r) = entry.path().strip_prefix(directory) {
+            if !rules.is_excluded(r) {
+                files.push((entry.path().to_path_buf(), r.to_path_buf()));
+            }
+        }
+    }
+
+    files
+}
@@ -0,0 +1,140 @@
+//! Provides file includes, which splice the expressions of another Cobalt file into a page.
+//! 
+//! `\include{partials/nav.co}` is resolved relative to the including file, and
+//! `\include{/partials/nav.co}` relative to the source directory.  Files whose name or
+//! directory begins with `_` are partials: they can be included but are not compiled alone.
+
+use std::path::{Path, PathBuf};
+
+use crate::{
+    parser::Expression,
+    error::{throw, Error},
+    files::{read, normalize},
+    cache::Dependency,
+    pass::{Pass, Context},
+    vars,
+};
+
+
+/// Expands the includes of a page, recording every included file as a dependency.
+pub struct IncludePass;
+
+impl Pass for IncludePass {
+    /// Replaces every `\include{...}` with the included expressions.
+    fn run(&self, expressions: Vec<Expression>, context: &Context) -> Vec<Expression> {
+        let build = context.get_build();
+        let mut dependencies = Vec::new();
+
+        let parse = |s| build.parse(s);
+        let expanded = expand(expressions, build.get_source(), context.get_page(), &parse, &mut dependencies);
+
+        for dependency in dependencies {
+            context.depend(dependency);
+        }
+
+        expanded
+    }
+}
+
+
+/// Checks whether a source file, relative to the source directory, is a partial.
+pub fn is_partial(page: &Path) -> bool {
+    page.components()
+        .any(|c| c.as_os_str().to_string_lossy().starts_with('_'))
+}
+
+
+/// Replaces every `\include{...}` in the expressions of a page with the included expressions.
+/// 
+/// `page` is relative to the `source` directory and included files are parsed with `parse`.
+/// Each included file is recorded in `dependencies`.
+pub fn expand<F>(
+    expressions: Vec<Expression>,
+    source: &Path,
+    page: &Path,
+    parse: &F,
+    dependencies: &mut Vec<Dependency>,
+) -> Vec<Expression>
+where
+    F: Fn(String) -> Vec<Expression>,
+{
+    let mut chain = vec![page.to_path_buf()];
+
+    expand_from(expressions, source, &mut chain, parse, dependencies)
+}
+
+
+/// Expands the includes of the last file in the chain of files currently being included.
+fn expand_from<F>(
+    expressions: Vec<Expression>,
+    source: &Path,
+    chain: &mut Vec<PathBuf>,
+    parse: &F,
+    dependencies: &mut Vec<Dependency>,
+) -> Vec<Expression>
+where
+    F: Fn(String) -> Vec<Expression>,
+{
+    let mut expanded = Vec::new();
+
+    for expr in expressions {
+        match expr {
+            Expression::Ctrl { keyword: k, argument: a, .. } if k == "include" => {
+                let file = resolve(&a, &chain[chain.len() - 1]);
+
+                if chain.contains(&file) {
+                    chain.push(file);
+                    let cycle: Vec<String> = chain.iter().map(|f| f.display().to_string()).collect();
+                    throw(Error::IncludeCycle (cycle.join(" -> ")));
+                }
+
+                let path = source.join(&file);
+                let dependency = Dependency::Contents (path.clone());
+                if !dependencies.contains(&dependency) {
+                    dependencies.push(dependency);
+                }
+                if !path.is_file() {
+                    throw(Error::CouldNotFindInclude (a));
+                }
+
+                // The front matter of an included file is ignored.
+                let data = read(&path);
+                let included = parse(vars::front_matter(&data).1.to_string());
+
+                chain.push(file);
+                expanded.append(&mut expand_from(included, source, chain, parse, dependencies));
+                chain.pop();
+            },
+            Expression::Fixed (expressions) => {
+                expanded.push(Expression::Fixed (expand_from(expressions, source, chain, parse, dependencies)));
+            },
+            Expression::Custom { name: n, value: v, children: c } => {
+                expanded.push(Expression::Custom {
+                    name: n,
+                    value: v,
+                    children: expand_from(c, source, chain, parse, dependencies),
+                });
+            },
+            _ => expanded.push(expr),
+        }
+    }
+
+    expanded
+}
+
+
+/// Resolves the path of an included file relative to the source directory.
+fn resolve(include: &str, file: &Path) -> PathBuf {
+    let resolved = match include.strip_prefix('/') {
+        Some(i) => normalize(Path::new(i)),
+        None => match file.parent() {
+            Some(d) => normalize(&d.join(include)),
+            None => normalize(Path::new(include)),
+        },
+    };
+
+    match resolved {
+        Some(r) => r,
+        None => throw(Error::CouldNotFindInclude (include.to_string())),
+    }
+}
@@ -0,0 +1,35 @@
+//! Provides the Cobalt web framework as a library.
+//! 
+//! Besides the `cobalt` executable, downstream crates may compile sites with custom
+//! syntax by registering extensions in a `syntax::Syntax` and passing it to `build::Build`.
+
+pub mod tokenizer;
+pub mod parser;
+pub mod emitter;
+pub mod error;
+pub mod config;
+pub mod files;
+pub mod build;
+pub mod glob;
+pub mod assets;
+pub mod log;
+pub mod cli;
+pub mod scaffold;
+pub mod manifest;
+pub mod watch;
+pub mod serve;
+pub mod cache;
+pub mod stage;
+pub mod ignore;
+pub mod template;
+pub mod include;
+pub mod vars;
+pub mod data;
+pub mod syntax;
+pub mod pass;
+pub mod plugin;
+pub mod backend;
+pub mod ast;
+pub mod sitemap;
+
+pub use config::Config;
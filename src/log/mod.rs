@@ -0,0 +1,50 @@
+//! Provides console output for the Cobalt framework, honoring the chosen verbosity.
+
+use std::sync::atomic::{AtomicU8, Ordering};
+
+use colored::*;
+
+/// Enumerates the levels of console output.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug)]
+pub enum Verbosity {
+    Quiet = 0,
+    Normal = 1,
+    Verbose = 2,
+}
+
+static VERBOSITY: AtomicU8 = AtomicU8::new(Verbosity::Normal as u8);
+
+/// Sets the verbosity of all subsequent console output.
+pub fn set_verbosity(verbosity: Verbosity) {
+    VERBOSITY.store(verbosity as u8, Ordering::Relaxed);
+}
+
+/// Gets the current verbosity.
+pub fn verbosity() -> Verbosity {
+    match VERBOSITY.load(Ordering::Relaxed) {
+        0 => Verbosity::Quiet,
+        1 => Verbosity::Normal,
+        _ => Verbosity::Verbose,
+    }
+}
+
+/// Prints a message unless output is quiet.
+pub fn info(msg: &str) {
+    if verbosity() >= Verbosity::Normal {
+        println!("{}", msg);
+    }
+}
+
+/// Prints a message only if output is verbose.
+pub fn verbose(msg: &str) {
+    if verbosity() >= Verbosity::Verbose {
+        println!("{}", msg);
+    }
+}
+
+/// Prints a warning to standard error unless output is quiet.
+pub fn warn(msg: &str) {
+    if verbosity() >= Verbosity::Normal {
+        eprintln!("{} {}", "warning:".yellow().bold(), msg);
+    }
+}
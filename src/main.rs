@@ -1,159 +1,161 @@
 //! Main executable for the Cobalt web framework.
 
-pub mod tokenizer;
-pub mod parser;
-pub mod emitter;
-pub mod error;
-
 use std::{
-    fs::{
-        self,
-        OpenOptions,
-    },
-    path::Path,
     env,
-    io::Write,
-    ffi::OsStr,
+    io::{self, Read},
+    path::{Path, PathBuf},
 };
 
-use error::{throw, Error};
-
-use serde_derive::Deserialize;
-use walkdir::WalkDir;
-use tokenizer::Tokenizer;
-use parser::Parser;
-use emitter::Emitter;
+use cobalt_web::{
+    error::{throw, Error},
+    Config,
+    build::Build,
+    cli::{Cli, Command},
+    scaffold::{self, Template},
+    files,
+    serve,
+    watch,
+    vars,
+    tokenizer::Tokenizer,
+    syntax::Syntax,
+    ast::{Ast, Tokens},
+};
 
-/// Holds website configuration information.
-#[derive(Clone, Deserialize)]
-pub struct Config {
-    site: Site,
-    style: Style,
+fn main() {
+    let cli = Cli::parse(env::args().skip(1));
+
+    match cli.command {
+        Command::Build => {
+            cli.reject_args();
+            load(&cli).run();
+        },
+        Command::Check => {
+            cli.reject_args();
+            load(&cli).check();
+        },
+        Command::New => match cli.args.as_slice() {
+            [d] => scaffold::new(Path::new(d), template(&cli)),
+            [] => throw(Error::ExpectedArgument ("new".to_string())),
+            [_, a, ..] => throw(Error::InvalidArgument (a.to_string())),
+        },
+        Command::Init => {
+            cli.reject_args();
+            scaffold::init(Path::new("."), template(&cli));
+        },
+        Command::Clean => {
+            cli.reject_args();
+            load(&cli).clean();
+        },
+        Command::Render => match cli.args.as_slice() {
+            [f] => render(&cli, f),
+            [] => throw(Error::ExpectedArgument ("render".to_string())),
+            [_, a, ..] => throw(Error::InvalidArgument (a.to_string())),
+        },
+        Command::Ast | Command::Tokens => match cli.args.as_slice() {
+            [f] => dump(cli.command, f),
+            [] => throw(Error::ExpectedArgument (format!("{:?}", cli.command).to_lowercase())),
+            [_, a, ..] => throw(Error::InvalidArgument (a.to_string())),
+        },
+        Command::Watch => {
+            cli.reject_args();
+            watch::watch(|| load(&cli), |_, _| ());
+        },
+        Command::Serve => {
+            cli.reject_args();
+            serve::serve(|| load(&cli), cli.port.unwrap_or(4000));
+        },
+    }
 }
 
-/// Holds general information about the website.
-#[derive(Clone, Deserialize)]
-pub struct Site {
-    // Holds the website name.
-    name: String,
-
-    // Holds optional instructions on how to format the page title.
-    //
-    // Options are "page", "site", "page | site", and "site | page".
-    // If not specified, Cobalt defaults to "page".
-    title: Option<String>,
-
-    // Holds the Cobalt source directory.
-    // 
-    // If not specified, Cobalt defaults to the current directory.
-    path: Option<String>,
-}
 
-/// Holds information about the website's CSS style.
-#[derive(Clone, Deserialize)]
-pub struct Style {
-    default: String,
-    external: Option<Vec<String>>,
+/// Imports and parses the configuration file and prepares a build of the site.
+fn load(cli: &Cli) -> Build {
+    match search(cli) {
+        Some(b) => b,
+        None => throw(Error::CouldNotFindToml),
+    }
 }
 
-fn main() {
-    // Import and parse the configuration file.
-    let mut config: String = String::new();
-    let mut dir: String = String::new();
-
-    // Recurse through current and parent directories to find configuration file.
-    let working_dir = match env::current_dir() {
-        Ok(p) => p,
-        Err(_) => throw(Error::CouldNotFindToml),
-    };
 
-    for path in working_dir.as_path().ancestors() {
-        let config_path_buf = path.join("cobalt.toml");
-        let config_path = config_path_buf.as_path();
-        let string = match config_path.to_path_buf().into_os_string().into_string() {
-            Ok(c) => c,
-            Err(_) => throw(Error::CouldNotFindToml),
-        };
-        let parent_dir = match config_path.parent() {
-            Some(p) => p,
+/// Prepares a build of the site if a configuration file can be found.
+fn search(cli: &Cli) -> Option<Build> {
+    let (config, root) = match &cli.config {
+        Some(c) => match c.parent() {
+            Some(p) => (Config::load(c), p.to_path_buf()),
             None => throw(Error::CouldNotFindToml),
-        };
-        let dir_string = match parent_dir.to_path_buf().into_os_string().into_string() {
-            Ok(c) => c,
-            Err(_) => throw(Error::CouldNotFindToml),
-        };
-        if config_path.is_file() {
-            config = string;
-            dir = dir_string;
-            break;
-        }
+        },
+        None => Config::search()?,
+    };
+
+    let mut build = Build::new(config, &root, cli.output.to_owned());
+    if let Some(j) = cli.jobs {
+        build.set_jobs(j);
+    }
+    build.set_dry_run(cli.dry_run);
+    if let Some(f) = cli.format {
+        build.set_format(f);
     }
 
-    let file = read(&config);
-    let toml: Config = match toml::from_str(&file) {
-        Ok(t) => t,
-        Err(_) => throw(Error::CouldNotFindToml),
-    };
+    Some(build)
+}
 
-    // Holds a list of source filenames to compile.
-    let mut filenames = Vec::new();
 
-    // Recursively walks through the current directory to search for source files.
-    let src_directory = match toml.site.path.clone() {
-        Some(s) => s,
-        None => dir,
+/// Renders a single file, or standard input if the file is `-`, to standard output.
+/// 
+/// A body-only fragment may be rendered without a configuration file.
+fn render(cli: &Cli, file: &str) {
+    let build = match search(cli) {
+        Some(b) => b,
+        None if cli.fragment => {
+            let mut build = Build::new(Config::default(), Path::new("."), None);
+            if let Some(f) = cli.format {
+                build.set_format(f);
+            }
+            build
+        },
+        None => throw(Error::CouldNotFindToml),
     };
 
-    for entry in WalkDir::new(&src_directory) {
-        let entry = match entry {
-            Ok(e) => e,
-            Err(_) => throw(Error::CouldNotReadFile),
-        };
-        if entry.path().extension() == Some(OsStr::new("co")) {
-            filenames.push(entry.path().display().to_string());
-        }
-    }
-
-    for filename in filenames {
-        let data = read(&filename);
-        let mut tokenizer = Tokenizer::new(data);
+    let page = if file == "-" {
+        PathBuf::from("index.co")
+    } else {
+        build.page_of(Path::new(file))
+    };
 
-        let parser = Parser::new();
-        let expressions = parser.parse_all(&mut tokenizer);
+    print!("{}", build.render(&page, read(file), cli.fragment));
+}
 
-        let emitter = Emitter::new(toml.to_owned());
-        let output = emitter.emit(expressions, &Path::new(&src_directory));
 
-        let mut output_filename = filename.clone();
-        output_filename.truncate(output_filename.len() - 3);
-        output_filename.push_str(".html");
+/// Prints the syntax tree or the tokens of a single file, or standard input if the file is `-`, as JSON.
+fn dump(command: Command, file: &str) {
+    let data = read(file);
+    let (front_matter, data) = vars::front_matter(&data);
 
-        write(&output_filename, output);
+    match command {
+        Command::Tokens => print!("{}", Tokens::new(Tokenizer::new(data.to_string()).collect()).to_json()),
+        _ => print!("{}", Ast::new(front_matter, Syntax::new().parse(data.to_string())).to_json()),
     }
 }
 
 
-/// Reads a file to a `String` or throws an error if impossible.
-fn read(filename: &str) -> String {
-    match fs::read_to_string(filename) {
-        Ok(f) => f,
-        Err(_) => throw(Error::CouldNotReadFile),
+/// Reads a file, or standard input if the file is `-`.
+fn read(file: &str) -> String {
+    if file != "-" {
+        return files::read(Path::new(file));
     }
+
+    let mut data = String::new();
+    if io::stdin().read_to_string(&mut data).is_err() {
+        throw(Error::CouldNotReadFile);
+    }
+    data
 }
 
 
-/// Writes a file from a `String` or throws an error if impossible.
-fn write(filename: &str, file: String) {
-    let mut output = match OpenOptions::new()
-        .create(true)
-        .write(true)
-        .truncate(true)
-        .open(filename.to_string()) {
-            Ok(f) => f,
-            Err(_) => throw(Error::CouldNotOpenFile (filename.to_string())),
-    };
-    match output.write_all(file.as_bytes()) {
-        Ok(_) => (),
-        Err(_) => throw(Error::CouldNotWriteFile (filename.to_string())),
-    };
-}
\ No newline at end of file
+/// Gets the starter template chosen on the command line.
+fn template(cli: &Cli) -> Template {
+    match &cli.template {
+        Some(t) => Template::from_name(t),
+        None => Template::Basic,
+    }
+}
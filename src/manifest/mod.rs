@@ -0,0 +1,160 @@
+//! Provides the build manifest, which records every file produced by a build.
+//! 
+//! The manifest lets Cobalt delete stale outputs without touching files it did not create.
+
+use std::{
+    collections::BTreeSet,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use colored::*;
+
+use crate::{
+    error::{throw, Error},
+    files::{write, normalize},
+    log,
+};
+
+
+/// Holds the name of the manifest file within the output directory.
+pub const MANIFEST: &str = ".cobalt-manifest";
+
+const HEADER: &str = "# Files produced by Cobalt.  Do not edit.";
+
+
+/// Holds the paths of all produced files, relative to the output directory.
+#[derive(Default)]
+pub struct Manifest {
+    files: BTreeSet<PathBuf>,
+}
+
+impl Manifest {
+    /// Constructs a new, empty manifest.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Loads the manifest of an output directory, which is empty if none exists.
+    /// 
+    /// Entries that are absolute or escape the output directory are dropped.
+    pub fn load(output: &Path) -> Self {
+        let files = match fs::read_to_string(output.join(MANIFEST)) {
+            Ok(f) => f
+                .lines()
+                .filter(|l| !l.is_empty() && !l.starts_with('#'))
+                .filter_map(|l| normalize(Path::new(l)))
+                .filter(|p| !p.as_os_str().is_empty())
+                .collect(),
+            Err(_) => BTreeSet::new(),
+        };
+
+        Self {
+            files,
+        }
+    }
+
+    /// Writes the manifest into an output directory.
+    pub fn save(&self, output: &Path) {
+        let mut file = format!("{}\n", HEADER);
+        for path in &self.files {
+            file.push_str(&format!("{}\n", path.display()));
+        }
+        write(&output.join(MANIFEST), file);
+    }
+
+    /// Records a produced file.
+    pub fn insert(&mut self, path: &Path) {
+        self.files.insert(path.to_path_buf());
+    }
+
+    /// Checks whether a file was produced.
+    pub fn contains(&self, path: &Path) -> bool {
+        self.files.contains(path)
+    }
+
+    /// Gets the files recorded in this manifest but not in a newer one.
+    pub fn stale(&self, newer: &Manifest) -> Vec<PathBuf> {
+        self.files.difference(&newer.files).cloned().collect()
+    }
+
+    /// Gets all recorded files.
+    pub fn files(&self) -> Vec<PathBuf> {
+        self.files.iter().cloned().collect()
+    }
+}
+
+
+/// Deletes produced files from an output directory, along with any directories left empty.
+/// 
+/// Files that are absolute or escape the output directory are never deleted.
+/// In a dry run, the files are only printed.  Returns the number of files deleted.
+pub fn delete(output: &Path, files: &[PathBuf], dry_run: bool) -> usize {
+    let mut deleted = 0;
+
+    for file in files {
+        let path = match normalize(file) {
+            Some(f) => output.join(f),
+            None => continue,
+        };
+        if !path.is_file() {
+            continue;
+        }
+        if dry_run {
+            log::info(&format!("{} {}", "would delete".cyan(), path.display()));
+            deleted += 1;
+            continue;
+        }
+        if fs::remove_file(&path).is_err() {
+            throw(Error::CouldNotDeleteFile (path.display().to_string()));
+        }
+        log::verbose(&format!("{} {}", " deleted".yellow(), path.display()));
+        deleted += 1;
+
+        // Remove parent directories until one is not empty.
+        let mut parent = path.parent();
+        while let Some(p) = parent {
+            if p == output || fs::remove_dir(p).is_err() {
+                break;
+            }
+            parent = p.parent();
+        }
+    }
+
+    deleted
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn load_drops_entries_outside_the_output_directory() {
+        let output = std::env::temp_dir().join(format!("cobalt-manifest-{}", std::process::id()));
+        write(&output.join(MANIFEST), format!(
+            "{}\nindex.html\nblog/../about.html\n../evil.txt\n/tmp/abs.txt\nblog/../../evil.txt\n.\n",
+            HEADER,
+        ));
+
+        let manifest = Manifest::load(&output);
+        assert_eq!(manifest.files(), vec![PathBuf::from("about.html"), PathBuf::from("index.html")]);
+
+        let _ = fs::remove_dir_all(&output);
+    }
+
+    #[test]
+    fn delete_skips_paths_outside_the_output_directory() {
+        let root = std::env::temp_dir().join(format!("cobalt-delete-{}", std::process::id()));
+        let output = root.join("out");
+        write(&root.join("evil.txt"), String::new());
+        write(&output.join("index.html"), String::new());
+
+        let files = [PathBuf::from("../evil.txt"), root.join("evil.txt"), PathBuf::from("index.html")];
+        assert_eq!(delete(&output, &files, false), 1);
+        assert!(root.join("evil.txt").is_file());
+        assert!(!output.join("index.html").exists());
+
+        let _ = fs::remove_dir_all(&root);
+    }
+}
@@ -9,6 +9,8 @@ pub mod fixed_parselet;
 
 use std::collections::HashMap;
 
+use serde_derive::{Serialize, Deserialize};
+
 pub use crate::tokenizer::{
     Tokenizer,
     Token,
@@ -23,7 +25,11 @@ use hyperlink_parselet::HyperlinkParselet;
 use fixed_parselet::FixedParselet;
 
 /// Abstracts over different "expressions" in Cobalt.
-#[derive(Clone, Debug)]
+/// 
+/// In JSON, expressions are objects holding their `type` and `value`, e.g.
+/// `{"type": "paragraph", "value": "Hello"}`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "type", content = "value", rename_all = "snake_case")]
 pub enum Expression {
     Ctrl {
         keyword: String,
@@ -43,6 +49,16 @@ pub enum Expression {
     H5 (String),
     H6 (String),
     Fixed (Vec<Expression>),
+
+    // Holds raw HTML, such as the output of a plugin.
+    Html (String),
+
+    // Holds a node produced by a custom `Parselet` and emitted by the `Hook` of the same name.
+    Custom {
+        name: String,
+        value: String,
+        children: Vec<Expression>,
+    },
 }
 
 
@@ -51,6 +67,12 @@ pub struct Parser {
     parselets: HashMap<TokenType, Box<dyn Parselet>>,
 }
 
+impl Default for Parser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Parser {
     /// Constructs a new parser from a tokenizer.
     pub fn new() -> Self {
@@ -73,8 +95,15 @@ impl Parser {
         }
     }
 
+    /// Registers a parselet for a token type, replacing any parselet already registered for it.
+    /// 
+    /// This allows custom syntax read by a `Recogniser` to be parsed into `Expression::Custom`.
+    pub fn register(&mut self, token_type: TokenType, parselet: Box<dyn Parselet>) {
+        self.parselets.insert(token_type, parselet);
+    }
+
     /// Parses the next expression from the tokenizer.
-    fn parse(&self, tokenizer: &mut Tokenizer) -> Option<Expression> {
+    pub fn parse(&self, tokenizer: &mut Tokenizer) -> Option<Expression> {
         let token = tokenizer.next()?;
 
         let parselet = self.parselets.get(&token.get_type())?;
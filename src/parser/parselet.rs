@@ -1,5 +1,7 @@
 //! Creates an interface for parselets.
 
+use std::sync::Arc;
+
 use crate::parser::{
     Parser,
     Tokenizer,
@@ -7,6 +9,13 @@ use crate::parser::{
     Expression,
 };
 
-pub trait Parselet {
+pub trait Parselet: Send + Sync {
     fn parse(&self, parser: &Parser, tokenizer: &mut Tokenizer, token: Token) -> Expression;
+}
+
+/// Allows a single parselet to be registered with many parsers.
+impl<P: Parselet + ?Sized> Parselet for Arc<P> {
+    fn parse(&self, parser: &Parser, tokenizer: &mut Tokenizer, token: Token) -> Expression {
+        (**self).parse(parser, tokenizer, token)
+    }
 }
\ No newline at end of file
@@ -0,0 +1,48 @@
+//! Provides a pass rewriting external links.
+
+use toml::value::Table;
+
+use crate::{
+    parser::Expression,
+    pass::{self, Pass, Context},
+};
+
+/// Rewrites the beginning of external links, given as prefix pairs in the pass's options,
+/// e.g. `"http://" = "https://"` in `[passes.external-links]`.
+pub struct ExternalLinksPass {
+    rewrites: Vec<(String, String)>,
+}
+
+impl ExternalLinksPass {
+    /// Constructs the pass from its options in `[passes.external-links]`.
+    pub fn new(options: Option<&Table>) -> Self {
+        let mut rewrites: Vec<(String, String)> = options
+            .into_iter()
+            .flatten()
+            .filter_map(|(k, v)| Some((k.to_owned(), v.as_str()?.to_string())))
+            .collect();
+
+        // Longer prefixes are more specific and take precedence.
+        rewrites.sort_by_key(|(k, _)| std::cmp::Reverse(k.len()));
+
+        Self {
+            rewrites,
+        }
+    }
+}
+
+impl Pass for ExternalLinksPass {
+    /// Rewrites every hyperlink beginning with one of the prefixes.
+    fn run(&self, expressions: Vec<Expression>, _context: &Context) -> Vec<Expression> {
+        pass::visit(expressions, &mut |expr| match expr {
+            Expression::Hyperlink { text: t, href: h } => {
+                let href = match self.rewrites.iter().find(|(p, _)| h.starts_with(p.as_str())) {
+                    Some((p, r)) => format!("{}{}", r, &h[p.len()..]),
+                    None => h,
+                };
+                vec![Expression::Hyperlink { text: t, href }]
+            },
+            e => vec![e],
+        })
+    }
+}
@@ -0,0 +1,160 @@
+//! Provides passes, which transform the expressions of a page between parsing and emission.
+//! 
+//! Every page runs through a pipeline of passes: the built-in include, variable and plugin passes,
+//! then the passes listed in the `[passes]` table of `cobalt.toml`, then any passes added
+//! from code with `Build::add_pass()`.  Drafts are stripped before variables are substituted.
+
+pub mod number_headings_pass;
+pub mod strip_drafts_pass;
+pub mod external_links_pass;
+
+use std::{
+    cell::RefCell,
+    path::Path,
+};
+
+use toml::value::Table;
+
+use crate::{
+    parser::Expression,
+    error::{throw, Error},
+    build::Build,
+    cache::Dependency,
+    include::IncludePass,
+    vars::VarsPass,
+    plugin::PluginPass,
+    Config,
+};
+
+pub use number_headings_pass::NumberHeadingsPass;
+pub use strip_drafts_pass::StripDraftsPass;
+pub use external_links_pass::ExternalLinksPass;
+
+
+/// Transforms the expressions of a page.
+pub trait Pass: Send + Sync {
+    fn run(&self, expressions: Vec<Expression>, context: &Context) -> Vec<Expression>;
+}
+
+
+/// Holds the page being transformed and the files it depends on.
+pub struct Context<'a> {
+    build: &'a Build,
+    page: &'a Path,
+    front_matter: &'a Table,
+    dependencies: RefCell<Vec<Dependency>>,
+}
+
+impl<'a> Context<'a> {
+    /// Constructs a new context for a page, given relative to the source directory.
+    pub fn new(build: &'a Build, page: &'a Path, front_matter: &'a Table) -> Self {
+        Self {
+            build,
+            page,
+            front_matter,
+            dependencies: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// Gets the build the page belongs to.
+    pub fn get_build(&self) -> &Build {
+        self.build
+    }
+
+    /// Gets the path of the page relative to the source directory.
+    pub fn get_page(&self) -> &Path {
+        self.page
+    }
+
+    /// Gets the front matter of the page.
+    pub fn get_front_matter(&self) -> &Table {
+        self.front_matter
+    }
+
+    /// Records a file that the page depends on.
+    pub fn depend(&self, dependency: Dependency) {
+        let mut dependencies = self.dependencies.borrow_mut();
+        if !dependencies.contains(&dependency) {
+            dependencies.push(dependency);
+        }
+    }
+
+    /// Gets the files that the passes recorded so far.
+    pub fn dependencies(&self) -> Vec<Dependency> {
+        self.dependencies.borrow().to_owned()
+    }
+}
+
+
+/// Holds the ordered passes run on every page.
+pub struct Pipeline {
+    passes: Vec<Box<dyn Pass>>,
+}
+
+impl Pipeline {
+    /// Constructs the pipeline of a site: the built-in passes followed by those named in `[passes]`.
+    /// 
+    /// `strip-drafts` instead runs right after includes are expanded, so that drafts may use
+    /// undefined variables and never run plugins.
+    pub fn new(config: &Config) -> Self {
+        let mut passes: Vec<Box<dyn Pass>> = vec![Box::new(IncludePass)];
+        let mut configured: Vec<Box<dyn Pass>> = Vec::new();
+
+        if let Some(p) = &config.passes {
+            for name in &p.run {
+                let options = p.options.get(name).and_then(|o| o.as_table());
+                match name.as_str() {
+                    "number-headings" => configured.push(Box::new(NumberHeadingsPass::new(options))),
+                    "strip-drafts" => passes.push(Box::new(StripDraftsPass)),
+                    "external-links" => configured.push(Box::new(ExternalLinksPass::new(options))),
+                    _ => throw(Error::UnknownPass (name.to_owned())),
+                }
+            }
+        }
+
+        passes.push(Box::new(VarsPass));
+        passes.push(Box::new(PluginPass::new()));
+        passes.append(&mut configured);
+
+        Self {
+            passes,
+        }
+    }
+
+    /// Adds a pass to the end of the pipeline.
+    pub fn push(&mut self, pass: Box<dyn Pass>) {
+        self.passes.push(pass);
+    }
+
+    /// Runs every pass in order.
+    pub fn run(&self, expressions: Vec<Expression>, context: &Context) -> Vec<Expression> {
+        self.passes.iter().fold(expressions, |e, p| p.run(e, context))
+    }
+}
+
+
+/// Visits every expression, including those nested in fixed `div`s and custom expressions,
+/// replacing each by the expressions `f` returns.
+/// 
+/// Nested expressions are visited before the expression containing them.
+pub fn visit<F>(expressions: Vec<Expression>, f: &mut F) -> Vec<Expression>
+where
+    F: FnMut(Expression) -> Vec<Expression>,
+{
+    let mut visited = Vec::new();
+
+    for expr in expressions {
+        let expr = match expr {
+            Expression::Fixed (e) => Expression::Fixed (visit(e, f)),
+            Expression::Custom { name: n, value: v, children: c } => Expression::Custom {
+                name: n,
+                value: v,
+                children: visit(c, f),
+            },
+            e => e,
+        };
+        visited.append(&mut f(expr));
+    }
+
+    visited
+}
@@ -0,0 +1,66 @@
+//! Provides a pass numbering headings, e.g. `2.1 Installation`.
+
+use toml::value::Table;
+
+use crate::{
+    parser::Expression,
+    pass::{self, Pass, Context},
+};
+
+/// Numbers headings hierarchically, starting at the level given by the `from` option.
+pub struct NumberHeadingsPass {
+    from: usize,
+}
+
+impl NumberHeadingsPass {
+    /// Constructs the pass from its options in `[passes.number-headings]`.
+    /// 
+    /// If `from` is not specified, Cobalt numbers every heading, starting with `H1`.
+    pub fn new(options: Option<&Table>) -> Self {
+        let from = options
+            .and_then(|o| o.get("from"))
+            .and_then(|f| f.as_integer())
+            .map_or(1, |f| f.clamp(1, 6) as usize);
+
+        Self {
+            from,
+        }
+    }
+}
+
+impl Pass for NumberHeadingsPass {
+    /// Prefixes every heading at or below the starting level with its number.
+    fn run(&self, expressions: Vec<Expression>, _context: &Context) -> Vec<Expression> {
+        let mut counters = [0; 6];
+
+        pass::visit(expressions, &mut |expr| {
+            let (level, text) = match &expr {
+                Expression::H1 (s) => (1, s),
+                Expression::H2 (s) => (2, s),
+                Expression::H3 (s) => (3, s),
+                Expression::H4 (s) => (4, s),
+                Expression::H5 (s) => (5, s),
+                Expression::H6 (s) => (6, s),
+                _ => return vec![expr],
+            };
+            if level < self.from {
+                return vec![expr];
+            }
+
+            counters[level - 1] += 1;
+            counters[level..].iter_mut().for_each(|c| *c = 0);
+
+            let number: Vec<String> = counters[self.from - 1..level].iter().map(|c| c.to_string()).collect();
+            let text = format!("{} {}", number.join("."), text);
+
+            vec![match level {
+                1 => Expression::H1 (text),
+                2 => Expression::H2 (text),
+                3 => Expression::H3 (text),
+                4 => Expression::H4 (text),
+                5 => Expression::H5 (text),
+                _ => Expression::H6 (text),
+            }]
+        })
+    }
+}
@@ -0,0 +1,48 @@
+//! Provides a pass removing drafts from pages.
+
+use crate::{
+    parser::Expression,
+    error::{throw, Error},
+    pass::{Pass, Context},
+};
+
+/// Removes every block between `\draft{}` and `\enddraft{}`.
+pub struct StripDraftsPass;
+
+impl Pass for StripDraftsPass {
+    /// Removes drafts, including drafts nested in fixed `div`s.
+    fn run(&self, expressions: Vec<Expression>, _context: &Context) -> Vec<Expression> {
+        strip(expressions)
+    }
+}
+
+
+/// Removes the drafts from a vector of expressions.
+fn strip(expressions: Vec<Expression>) -> Vec<Expression> {
+    let mut stripped = Vec::new();
+    let mut depth = 0;
+
+    for expr in expressions {
+        let keyword = match &expr {
+            Expression::Ctrl { keyword: k, .. } => k.as_str(),
+            _ => "",
+        };
+
+        match keyword {
+            "draft" => depth += 1,
+            "enddraft" if depth == 0 => throw(Error::UnmatchedBlock (keyword.to_string())),
+            "enddraft" => depth -= 1,
+            _ if depth > 0 => (),
+            _ => stripped.push(match expr {
+                Expression::Fixed (e) => Expression::Fixed (strip(e)),
+                e => e,
+            }),
+        }
+    }
+
+    if depth > 0 {
+        throw(Error::UnmatchedBlock ("draft".to_string()));
+    }
+
+    stripped
+}
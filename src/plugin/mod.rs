@@ -0,0 +1,202 @@
+//! Provides external plugins, which implement control sequences as local executables.
+//! 
+//! A plugin receives a JSON object with the `keyword`, `class`, `id` and `argument` of the
+//! control sequence and the `page` it appears on through standard input, and writes HTML, or
+//! Cobalt source if its output is "cobalt", to standard output.  Results are cached in
+//! `.cobalt-cache/plugins` by a hash of the plugin and its input.
+
+use std::{
+    fs,
+    io::{Read, Write},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    parser::Expression,
+    error::{throw, Error},
+    files::{hash, write},
+    cache::{self, Dependency},
+    config::Plugin,
+    pass::{visit, Pass, Context},
+    include::IncludePass,
+    vars::VarsPass,
+};
+
+
+/// Holds the name of the plugin cache directory within the build cache.
+const PLUGINS: &str = "plugins";
+
+/// Holds the default number of seconds a plugin may run.
+const TIMEOUT: u64 = 10;
+
+/// Holds the maximum number of plugins whose Cobalt output may nest within each other.
+const DEPTH: usize = 16;
+
+
+/// Replaces every control sequence implemented by a plugin with the plugin's output.
+/// 
+/// Cobalt output is parsed and runs through the include, variable and plugin passes again,
+/// up to `DEPTH` plugins deep.
+#[derive(Default)]
+pub struct PluginPass {
+    depth: usize,
+}
+
+impl PluginPass {
+    /// Constructs a new plugin pass for the expressions of a page.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Pass for PluginPass {
+    /// Runs the plugins of a page, including within fixed `div`s and custom expressions.
+    fn run(&self, expressions: Vec<Expression>, context: &Context) -> Vec<Expression> {
+        let build = context.get_build();
+        let plugins = match &build.get_config().plugins {
+            Some(p) => p,
+            None => return expressions,
+        };
+
+        visit(expressions, &mut |expr| match expr {
+            Expression::Ctrl { keyword: k, class: c, id: i, argument: a } if plugins.contains_key(&k) => {
+                let plugin = &plugins[&k];
+                let input = serde_json::json!({
+                    "keyword": k,
+                    "class": c,
+                    "id": i,
+                    "argument": a,
+                    "page": context.get_page().display().to_string(),
+                });
+                let output = run(&k, plugin, &input.to_string(), context);
+
+                match plugin.get_output() {
+                    "html" => vec![Expression::Html (output)],
+                    "cobalt" => {
+                        if self.depth >= DEPTH {
+                            throw(Error::PluginFailed (k, format!("output nested more than {} plugins deep", DEPTH)));
+                        }
+                        let expressions = IncludePass.run(build.parse(output), context);
+                        let expressions = VarsPass.run(expressions, context);
+                        Self { depth: self.depth + 1 }.run(expressions, context)
+                    },
+                    o => throw(Error::InvalidConfig (o.to_string())),
+                }
+            },
+            e => vec![e],
+        })
+    }
+}
+
+
+/// Runs a plugin with the given input, or gets its output from the cache.
+fn run(keyword: &str, plugin: &Plugin, input: &str, context: &Context) -> String {
+    let build = context.get_build();
+    let command = resolve(plugin.get_command(), build.get_root());
+
+    // The plugin is rerun whenever its executable changes.
+    let executable = fs::read(&command).unwrap_or_default();
+    if command.is_file() {
+        context.depend(Dependency::Contents (command.clone()));
+    }
+
+    let key = hash(format!("{}\n{:016x}\n{}", plugin.get_command(), hash(&executable), input).as_bytes());
+    let cached = build.get_root().join(cache::CACHE).join(PLUGINS).join(format!("{:016x}", key));
+    if let Ok(output) = fs::read_to_string(&cached) {
+        return output;
+    }
+
+    let output = execute(keyword, &command, input, plugin.get_timeout().unwrap_or(TIMEOUT), build.get_root());
+
+    if !build.is_dry_run() && !build.is_checking() {
+        write(&cached, output.to_owned());
+    }
+
+    output
+}
+
+
+/// Resolves the command of a plugin: paths are relative to `cobalt.toml`, while bare
+/// names are looked up in `PATH`.
+fn resolve(command: &str, root: &Path) -> PathBuf {
+    if command.contains('/') {
+        root.join(command)
+    } else {
+        PathBuf::from(command)
+    }
+}
+
+
+/// Gets the path of a plugin executable within the site, if its command is a path.
+pub fn local(command: &str, root: &Path) -> Option<PathBuf> {
+    Some(resolve(command, root)).filter(|_| command.contains('/'))
+}
+
+
+/// Runs a plugin executable, killing it if it runs longer than `timeout` seconds.
+fn execute(keyword: &str, command: &Path, input: &str, timeout: u64, root: &Path) -> String {
+    let mut child = match Command::new(command)
+        .current_dir(root)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+    {
+        Ok(c) => c,
+        Err(e) => throw(Error::PluginFailed (keyword.to_string(), format!("could not run {}: {}", command.display(), e))),
+    };
+
+    // Write and read on separate threads so that a plugin filling a pipe cannot block.
+    let mut stdin = child.stdin.take();
+    let input = input.to_string();
+    let writer = thread::spawn(move || {
+        if let Some(s) = stdin.as_mut() {
+            let _ = s.write_all(input.as_bytes());
+        }
+    });
+    let stdout = read(child.stdout.take());
+    let stderr = read(child.stderr.take());
+
+    let deadline = Instant::now() + Duration::from_secs(timeout);
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(s)) => break s,
+            Ok(None) if Instant::now() < deadline => thread::sleep(Duration::from_millis(10)),
+            Ok(None) => {
+                let _ = child.kill();
+                let _ = child.wait();
+                throw(Error::PluginFailed (keyword.to_string(), format!("timed out after {} s", timeout)));
+            },
+            Err(e) => throw(Error::PluginFailed (keyword.to_string(), e.to_string())),
+        }
+    };
+
+    let _ = writer.join();
+    let stdout = stdout.join().unwrap_or_default();
+    let stderr = stderr.join().unwrap_or_default();
+
+    if !status.success() {
+        let message = match stderr.trim() {
+            "" => status.to_string(),
+            s => s.to_string(),
+        };
+        throw(Error::PluginFailed (keyword.to_string(), message));
+    }
+
+    stdout
+}
+
+
+/// Reads a pipe to a `String` on a separate thread.
+fn read<R: Read + Send + 'static>(pipe: Option<R>) -> thread::JoinHandle<String> {
+    thread::spawn(move || {
+        let mut s = String::new();
+        if let Some(mut p) = pipe {
+            let _ = p.read_to_string(&mut s);
+        }
+        s
+    })
+}
@@ -0,0 +1,126 @@
+//! Provides project scaffolding for `cobalt new` and `cobalt init`.
+
+use std::{
+    fs,
+    path::Path,
+};
+
+use colored::*;
+
+use crate::{
+    error::{throw, Error},
+    files::write,
+    log,
+};
+
+
+const CONFIG: &str = include_str!("templates/cobalt.toml");
+const GITIGNORE: &str = include_str!("templates/gitignore");
+const STYLESHEET: &str = include_str!("templates/style.css");
+const LOGO: &str = include_str!("templates/logo.svg");
+
+
+/// Enumerates the built-in starter templates.
+#[derive(Clone, Copy, Debug)]
+pub enum Template {
+    Basic,
+    Blog,
+    Docs,
+}
+
+impl Template {
+    /// Gets a starter template from its name.
+    pub fn from_name(name: &str) -> Self {
+        match name {
+            "basic" => Self::Basic,
+            "blog" => Self::Blog,
+            "docs" => Self::Docs,
+            _ => throw(Error::InvalidTemplate (name.to_string())),
+        }
+    }
+
+    /// Gets the pages of the template as pairs of paths and contents.
+    fn pages(&self) -> Vec<(&'static str, &'static str)> {
+        match self {
+            Self::Basic => vec![
+                ("index.co", include_str!("templates/basic/index.co")),
+            ],
+            Self::Blog => vec![
+                ("index.co", include_str!("templates/blog/index.co")),
+                ("posts/first-post.co", include_str!("templates/blog/first-post.co")),
+            ],
+            Self::Docs => vec![
+                ("index.co", include_str!("templates/docs/index.co")),
+                ("guide/getting-started.co", include_str!("templates/docs/getting-started.co")),
+            ],
+        }
+    }
+
+    /// Gets any additional `[site]` configuration of the template.
+    fn extra_config(&self) -> &'static str {
+        match self {
+            Self::Docs => "\n# Write each page to its own directory, e.g. 'guide/getting-started/'.\npretty_urls = true\n",
+            _ => "",
+        }
+    }
+}
+
+
+/// Creates a new site in a directory, which must not exist or be empty.
+pub fn new(directory: &Path, template: Template) {
+    if let Ok(mut entries) = fs::read_dir(directory) {
+        if entries.next().is_some() {
+            throw(Error::DirectoryNotEmpty (directory.display().to_string()));
+        }
+    }
+
+    init(directory, template);
+}
+
+
+/// Creates a new site in an existing directory that does not yet hold a `cobalt.toml`.
+/// 
+/// Existing files are never overwritten.
+pub fn init(directory: &Path, template: Template) {
+    if directory.join("cobalt.toml").exists() {
+        throw(Error::AlreadyInitialized (directory.display().to_string()));
+    }
+
+    // Name the site after its directory.
+    let absolute = fs::canonicalize(directory).unwrap_or(directory.to_path_buf());
+    let name = match absolute.file_name() {
+        Some(n) => n.to_string_lossy().to_string(),
+        None => "My Site".to_string(),
+    };
+
+    let config = CONFIG
+        .replace("{name}", &name)
+        .replace("{extra}", template.extra_config());
+
+    let mut files = vec![
+        ("cobalt.toml".to_string(), config),
+        (".gitignore".to_string(), GITIGNORE.to_string()),
+        ("style.css".to_string(), STYLESHEET.to_string()),
+        ("img/logo.svg".to_string(), LOGO.to_string()),
+    ];
+    for (path, contents) in template.pages() {
+        files.push((path.to_string(), contents.replace("{name}", &name)));
+    }
+
+    for (path, contents) in files {
+        let path = directory.join(path);
+        if path.exists() {
+            log::info(&format!("{} {} (already exists)", " skipped".yellow(), path.display()));
+            continue;
+        }
+        write(&path, contents);
+        log::verbose(&format!("{} {}", " created".green(), path.display()));
+    }
+
+    log::info(&format!(
+        "{} new site '{}' in {}, run 'cobalt build' there to build it",
+        "Created".green().bold(),
+        &name,
+        directory.display(),
+    ));
+}
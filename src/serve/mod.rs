@@ -0,0 +1,351 @@
+//! Provides a local development server with live reload.
+//! 
+//! The server rebuilds the site whenever its sources change and notifies open pages
+//! through server-sent events, so that the browser refreshes automatically.
+
+use std::{
+    fs,
+    io::{BufRead, BufReader, Write},
+    net::{TcpListener, TcpStream},
+    path::{Component, Path, PathBuf},
+    sync::{Condvar, Mutex},
+    thread,
+    time::Duration,
+};
+
+use colored::*;
+
+use crate::{
+    build::Build,
+    error::{throw, Error},
+    log,
+    watch,
+};
+
+
+/// Holds the path of the live reload event stream.
+const EVENTS: &str = "/__cobalt/events";
+
+/// Holds the live reload client injected into every HTML page.
+const CLIENT: &str = "<script>new EventSource(\"/__cobalt/events\").onmessage = function () { location.reload(); };</script>\n";
+
+/// Holds the interval between keep-alive messages on the event stream.
+const KEEP_ALIVE: Duration = Duration::from_secs(15);
+
+
+/// Holds the state of the latest build, shared between the watcher and the server.
+#[derive(Default)]
+struct State {
+    // Counts completed rebuilds, so that clients can tell when to reload.
+    generation: u64,
+
+    // Holds the error of the latest build, if it failed.
+    error: Option<String>,
+
+    // Holds the output directory of the latest build, which may change with the configuration.
+    output: Option<PathBuf>,
+}
+
+
+/// Holds the server's shared state.
+struct Server {
+    state: Mutex<State>,
+    changed: Condvar,
+}
+
+
+/// Builds the site, serves it on the given port and rebuilds it on change, until the process is stopped.
+pub fn serve<L: Fn() -> Build + Sync>(load: L, port: u16) {
+    let server = Server {
+        state: Mutex::new(State::default()),
+        changed: Condvar::new(),
+    };
+
+    let listener = match TcpListener::bind(("127.0.0.1", port)) {
+        Ok(l) => l,
+        Err(_) => throw(Error::CouldNotBind (port)),
+    };
+
+    thread::scope(|scope| {
+        scope.spawn(|| watch::watch(&load, |build, result| {
+            server.update(build.get_output(), result.err().map(|e| e.message()))
+        }));
+
+        log::info(&format!("{} on http://localhost:{}/", "Serving".cyan().bold(), port));
+
+        for stream in listener.incoming().flatten() {
+            let server = &server;
+            scope.spawn(move || server.handle(stream));
+        }
+    });
+}
+
+
+impl Server {
+    /// Records the result of a rebuild and wakes up any waiting clients.
+    fn update(&self, output: &Path, error: Option<String>) {
+        let mut state = self.lock();
+        state.generation += 1;
+        state.error = error;
+        state.output = Some(output.to_path_buf());
+        self.changed.notify_all();
+    }
+
+    /// Locks the shared state, recovering it if another thread panicked while holding it.
+    fn lock(&self) -> std::sync::MutexGuard<'_, State> {
+        match self.state.lock() {
+            Ok(s) => s,
+            Err(e) => e.into_inner(),
+        }
+    }
+
+    /// Handles a single HTTP/1.1 request.
+    fn handle(&self, mut stream: TcpStream) {
+        let mut reader = match stream.try_clone() {
+            Ok(s) => BufReader::new(s),
+            Err(_) => return,
+        };
+
+        // Read the request line and discard the headers.
+        let mut request = String::new();
+        if reader.read_line(&mut request).is_err() {
+            return;
+        }
+        let mut line = String::new();
+        while reader.read_line(&mut line).is_ok_and(|n| n > 2) {
+            line.clear();
+        }
+
+        let mut parts = request.split_whitespace();
+        let method = parts.next().unwrap_or("");
+        let target = parts.next().unwrap_or("/");
+        let path = decode(target.split(['?', '#']).next().unwrap_or("/"));
+
+        if method != "GET" && method != "HEAD" {
+            respond(&mut stream, "405 Method Not Allowed", "text/plain", b"Method not allowed", method == "HEAD");
+            return;
+        }
+
+        if path == EVENTS {
+            self.events(stream);
+            return;
+        }
+
+        let head = method == "HEAD";
+        let (output, error) = {
+            let state = self.lock();
+            (state.output.clone(), state.error.clone())
+        };
+        if let Some(e) = error {
+            if path.ends_with('/') || path.ends_with(".html") {
+                respond(&mut stream, "500 Internal Server Error", "text/html", error_page(&e).as_bytes(), head);
+                return;
+            }
+        }
+
+        // Nothing is served until the first build finishes.
+        let output = match output {
+            Some(o) => o,
+            None => {
+                respond(&mut stream, "503 Service Unavailable", "text/plain", b"Building", head);
+                return;
+            },
+        };
+
+        let file = match resolve(&output, &path) {
+            Some(f) => f,
+            None => {
+                respond(&mut stream, "400 Bad Request", "text/plain", b"Bad request", head);
+                return;
+            },
+        };
+
+        if file.is_dir() {
+            if !path.ends_with('/') {
+                redirect(&mut stream, &format!("{}/", path));
+                return;
+            }
+            self.file(&mut stream, &output, "200 OK", &file.join("index.html"), head);
+        } else {
+            self.file(&mut stream, &output, "200 OK", &file, head);
+        }
+    }
+
+    /// Responds with a file from the output directory, or the not-found page if it does not exist.
+    fn file(&self, stream: &mut TcpStream, output: &Path, status: &str, file: &Path, head: bool) {
+        let contents = match fs::read(file) {
+            Ok(c) => c,
+            Err(_) if status == "200 OK" => {
+                return self.file(stream, output, "404 Not Found", &output.join("404.html"), head);
+            },
+            Err(_) => NOT_FOUND.as_bytes().to_vec(),
+        };
+
+        let mime = mime_type(file);
+        if mime == "text/html" {
+            respond(stream, status, mime, &inject(contents), head);
+        } else {
+            respond(stream, status, mime, &contents, head);
+        }
+    }
+
+    /// Holds an event stream open, sending a reload event after every rebuild.
+    fn events(&self, mut stream: TcpStream) {
+        let header = "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\nConnection: keep-alive\r\n\r\n";
+        if stream.write_all(header.as_bytes()).is_err() {
+            return;
+        }
+
+        let mut state = self.lock();
+        let mut generation = state.generation;
+        loop {
+            state = match self.changed.wait_timeout(state, KEEP_ALIVE) {
+                Ok((s, _)) => s,
+                Err(e) => e.into_inner().0,
+            };
+
+            // Send a reload event if a rebuild finished, or a comment to detect closed connections.
+            let message = if state.generation != generation {
+                generation = state.generation;
+                "data: reload\n\n"
+            } else {
+                ": keep-alive\n\n"
+            };
+            if stream.write_all(message.as_bytes()).and_then(|_| stream.flush()).is_err() {
+                return;
+            }
+        }
+    }
+}
+
+
+/// Holds the fallback page for missing files if the site has no `404.html`.
+const NOT_FOUND: &str = "<!DOCTYPE html>\n<html>\n<head>\n<title>Not Found</title>\n</head>\n<body>\n<h1>404 Not Found</h1>\n<p>This page does not exist.</p>\n</body>\n</html>\n";
+
+
+/// Writes a complete HTTP response and closes the connection.
+fn respond(stream: &mut TcpStream, status: &str, mime: &str, body: &[u8], head: bool) {
+    let header = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nCache-Control: no-cache\r\nConnection: close\r\n\r\n",
+        status,
+        mime,
+        body.len(),
+    );
+    let _ = stream.write_all(header.as_bytes());
+    if !head {
+        let _ = stream.write_all(body);
+    }
+}
+
+
+/// Redirects to another path on the server.
+fn redirect(stream: &mut TcpStream, location: &str) {
+    let header = format!(
+        "HTTP/1.1 301 Moved Permanently\r\nLocation: {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+        location,
+    );
+    let _ = stream.write_all(header.as_bytes());
+}
+
+
+/// Injects the live reload client into an HTML page, just before `</body>` if present.
+fn inject(page: Vec<u8>) -> Vec<u8> {
+    let page = String::from_utf8_lossy(&page).to_string();
+    match page.rfind("</body>") {
+        Some(i) => format!("{}{}{}", &page[..i], CLIENT, &page[i..]).into_bytes(),
+        None => format!("{}{}", page, CLIENT).into_bytes(),
+    }
+}
+
+
+/// Gets a page showing a build error, which reloads once the error is fixed.
+fn error_page(error: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<title>Cobalt Error</title>\n</head>\n<body>\n\
+        <h1>Cobalt could not build this site</h1>\n<pre>{}</pre>\n{}</body>\n</html>\n",
+        escape(error),
+        CLIENT,
+    )
+}
+
+
+/// Escapes text for inclusion in HTML.
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+
+/// Resolves a request path to a path in the output directory.
+/// 
+/// Returns `None` if the path would escape the output directory.
+fn resolve(output: &Path, path: &str) -> Option<PathBuf> {
+    let mut file = output.to_path_buf();
+
+    for component in Path::new(path.trim_start_matches('/')).components() {
+        match component {
+            Component::Normal(c) => file.push(c),
+            Component::CurDir => (),
+            _ => return None,
+        }
+    }
+
+    Some(file)
+}
+
+
+/// Decodes percent-encoded characters in a request path.
+fn decode(path: &str) -> String {
+    let bytes = path.as_bytes();
+    let mut decoded = Vec::new();
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let hex = bytes.get(i + 1..i + 3).and_then(|h| std::str::from_utf8(h).ok());
+        match (bytes[i], hex.and_then(|h| u8::from_str_radix(h, 16).ok())) {
+            (b'%', Some(b)) => {
+                decoded.push(b);
+                i += 3;
+            },
+            (b, _) => {
+                decoded.push(b);
+                i += 1;
+            },
+        }
+    }
+
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+
+/// Gets the MIME type of a file from its extension.
+fn mime_type(file: &Path) -> &'static str {
+    let extension = match file.extension() {
+        Some(e) => e.to_string_lossy().to_lowercase(),
+        None => String::new(),
+    };
+
+    match extension.as_str() {
+        "html" | "htm" => "text/html",
+        "css" => "text/css",
+        "js" | "mjs" => "text/javascript",
+        "json" => "application/json",
+        "xml" => "application/xml",
+        "txt" => "text/plain",
+        "svg" => "image/svg+xml",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "ico" => "image/x-icon",
+        "woff" => "font/woff",
+        "woff2" => "font/woff2",
+        "ttf" => "font/ttf",
+        "otf" => "font/otf",
+        "pdf" => "application/pdf",
+        "mp4" => "video/mp4",
+        "webm" => "video/webm",
+        "mp3" => "audio/mpeg",
+        "wasm" => "application/wasm",
+        _ => "application/octet-stream",
+    }
+}
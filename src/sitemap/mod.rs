@@ -0,0 +1,55 @@
+//! Provides the sitemap, which lists the URL of every page for search engines.
+//! 
+//! A sitemap needs absolute URLs, so Cobalt only writes `sitemap.xml` if `site.base_url`
+//! is an absolute URL with a scheme, e.g. `https://example.com/docs`.  Each URL follows the site's URL scheme, e.g. `/blog/post/` with pretty URLs.
+
+use std::path::PathBuf;
+
+use crate::build;
+
+
+/// Holds the name of the sitemap within the output directory.
+pub const SITEMAP: &str = "sitemap.xml";
+
+
+/// Generates the sitemap of the given pages, relative to the source directory.
+pub fn generate(pages: &[PathBuf], base_url: &str, pretty_urls: bool) -> String {
+    let base_url = base_url.trim_end_matches('/');
+
+    let mut sitemap = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    sitemap.push_str("<urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n");
+    for page in pages {
+        let url = format!("{}{}", base_url, build::page_url(page, pretty_urls, "html"));
+        sitemap.push_str(&format!("  <url><loc>{}</loc></url>\n", escape(&url)));
+    }
+    sitemap.push_str("</urlset>\n");
+
+    sitemap
+}
+
+
+/// Escapes the characters of a URL that are special in XML.
+fn escape(url: &str) -> String {
+    url.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generate_lists_absolute_urls() {
+        let pages = vec![PathBuf::from("index.co"), PathBuf::from("blog/a&b.co")];
+        let sitemap = generate(&pages, "https://example.com/", true);
+        assert!(sitemap.contains("<loc>https://example.com/</loc>"));
+        assert!(sitemap.contains("<loc>https://example.com/blog/a&amp;b/</loc>"));
+
+        let sitemap = generate(&pages, "https://example.com", false);
+        assert!(sitemap.contains("<loc>https://example.com/index.html</loc>"));
+    }
+}
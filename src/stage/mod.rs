@@ -0,0 +1,151 @@
+//! Provides staged builds, which move outputs into place only once the whole build succeeds.
+//! 
+//! Outputs are first written into a hidden staging directory within the output directory,
+//! on the same file system.  When the build succeeds, each staged file is moved next to its
+//! destination and only then renamed into place, so that a failed build never leaves the site
+//! half old and half new and no file is ever served partially written.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use colored::*;
+
+use crate::{
+    error::{throw, catch, Error},
+    files,
+    log,
+};
+
+
+/// Holds the name of the staging directory within the output directory.
+pub const STAGING: &str = ".cobalt-staging";
+
+
+/// Holds the files staged during a build.
+pub struct Stage {
+    directory: PathBuf,
+    output: PathBuf,
+    files: Mutex<Vec<PathBuf>>,
+    dry_run: bool,
+}
+
+impl Stage {
+    /// Constructs a new stage for a build, discarding anything left behind by a failed build.
+    /// 
+    /// In a dry run, nothing is written and staged files are only recorded.
+    pub fn new(output: &Path, dry_run: bool) -> Self {
+        let directory = output.join(STAGING);
+        if !dry_run && directory.exists() && fs::remove_dir_all(&directory).is_err() {
+            throw(Error::CouldNotDeleteFile (directory.display().to_string()));
+        }
+
+        Self {
+            directory,
+            output: output.to_path_buf(),
+            files: Mutex::new(Vec::new()),
+            dry_run,
+        }
+    }
+
+    /// Stages a file with the given contents, relative to the output directory.
+    pub fn write(&self, relative: &Path, contents: String) {
+        if !self.dry_run {
+            files::write(&self.directory.join(relative), contents);
+        }
+        self.record(relative);
+    }
+
+    /// Stages a copy of a file, relative to the output directory.
+    pub fn copy(&self, source: &Path, relative: &Path) {
+        if !self.dry_run {
+            files::copy(source, &self.directory.join(relative));
+        }
+        self.record(relative);
+    }
+
+    /// Records a staged file.
+    fn record(&self, relative: &Path) {
+        match self.files.lock() {
+            Ok(mut f) => f.push(relative.to_path_buf()),
+            Err(e) => e.into_inner().push(relative.to_path_buf()),
+        }
+    }
+
+    /// Moves every staged file into the output directory, or prints them in a dry run.
+    /// 
+    /// Every file is first moved to a temporary file next to its destination, so that nothing
+    /// in the output directory changes if any of them fails.  The temporary files are then
+    /// renamed into place, which is atomic.  Returns the paths of the files, relative to the
+    /// output directory.
+    pub fn commit(self) -> Vec<PathBuf> {
+        let mut staged = match self.files.into_inner() {
+            Ok(f) => f,
+            Err(e) => e.into_inner(),
+        };
+        // A file staged twice, e.g. a page and an asset of the same name, is moved once.
+        staged.sort();
+        staged.dedup();
+
+        if self.dry_run {
+            for relative in &staged {
+                log::info(&format!("{} {}", "would write".cyan(), self.output.join(relative).display()));
+            }
+            return staged;
+        }
+
+        let mut prepared = Vec::new();
+        for relative in &staged {
+            let destination = self.output.join(relative);
+            let temporary = temporary(&destination);
+
+            match catch(|| prepare(&self.directory.join(relative), &temporary)) {
+                Ok(_) => prepared.push((temporary, destination)),
+                Err(e) => {
+                    for (t, _) in &prepared {
+                        let _ = fs::remove_file(t);
+                    }
+                    let _ = fs::remove_dir_all(&self.directory);
+                    throw(e);
+                },
+            }
+        }
+
+        for (temporary, destination) in &prepared {
+            if fs::rename(temporary, destination).is_err() {
+                throw(Error::CouldNotWriteFile (destination.display().to_string()));
+            }
+            log::verbose(&format!("{} {}", "   wrote".green(), destination.display()));
+        }
+
+        let _ = fs::remove_dir_all(&self.directory);
+
+        staged
+    }
+}
+
+
+/// Gets the temporary file next to a destination, e.g. `.index.html.cobalt-tmp` for `index.html`.
+fn temporary(destination: &Path) -> PathBuf {
+    let name = match destination.file_name() {
+        Some(n) => n.to_string_lossy().to_string(),
+        None => String::new(),
+    };
+    destination.with_file_name(format!(".{}.cobalt-tmp", name))
+}
+
+
+/// Moves a staged file to a temporary file on the file system of its destination.
+/// 
+/// Renaming fails across file systems, e.g. if a directory within the output is a mount point,
+/// so the file is copied instead.
+fn prepare(source: &Path, temporary: &Path) {
+    if let Some(parent) = temporary.parent() {
+        files::create_dir(parent);
+    }
+    if fs::rename(source, temporary).is_err() {
+        files::copy(source, temporary);
+    }
+}
@@ -0,0 +1,63 @@
+//! Provides a registry of syntax extensions for the tokenizer, parser and emitter.
+//! 
+//! A syntax extension usually consists of a `Recogniser` reading a `TokenType::Custom`
+//! token, a `Parselet` turning that token into an `Expression::Custom` and a `Hook`
+//! emitting that expression.
+
+use std::sync::Arc;
+
+use crate::{
+    tokenizer::{Tokenizer, TokenType, Recogniser},
+    parser::{Parser, Parselet, Expression},
+    emitter::{Emitter, Hook},
+};
+
+
+/// Holds the syntax extensions used to compile pages.
+#[derive(Default)]
+pub struct Syntax {
+    recognisers: Vec<Box<dyn Recogniser>>,
+    parselets: Vec<(TokenType, Arc<dyn Parselet>)>,
+    hooks: Vec<(String, Box<dyn Hook>)>,
+}
+
+impl Syntax {
+    /// Constructs an empty registry, leaving the built-in syntax unchanged.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a recogniser with the tokenizer.
+    pub fn register_recogniser(&mut self, recogniser: Box<dyn Recogniser>) {
+        self.recognisers.push(recogniser);
+    }
+
+    /// Registers a parselet for a token type with the parser.
+    pub fn register_parselet(&mut self, token_type: TokenType, parselet: Box<dyn Parselet>) {
+        self.parselets.push((token_type, Arc::from(parselet)));
+    }
+
+    /// Registers a hook emitting the custom expressions of the given name.
+    pub fn register_hook(&mut self, name: &str, hook: Box<dyn Hook>) {
+        self.hooks.push((name.to_string(), hook));
+    }
+
+    /// Tokenizes and parses Cobalt source using the registered extensions.
+    pub fn parse(&self, source: String) -> Vec<Expression> {
+        let mut tokenizer = Tokenizer::with_recognisers(source, &self.recognisers);
+
+        let mut parser = Parser::new();
+        for (token_type, parselet) in &self.parselets {
+            parser.register(token_type.to_owned(), Box::new(Arc::clone(parselet)));
+        }
+
+        parser.parse_all(&mut tokenizer)
+    }
+
+    /// Registers every hook with an emitter.
+    pub fn register_hooks<'a>(&'a self, emitter: &mut Emitter<'a>) {
+        for (name, hook) in &self.hooks {
+            emitter.register(name, hook.as_ref());
+        }
+    }
+}
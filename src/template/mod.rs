@@ -0,0 +1,44 @@
+//! Provides placeholder substitution for user-defined templates such as layouts.
+
+/// Fills the `{name}` placeholders of a template with the values returned by `lookup`.
+/// 
+/// Names may contain letters, digits, `_`, `-` and `.`, e.g. `{site.name}`.  Placeholders
+/// for which `lookup` returns `None`, and braces not forming a placeholder, such as
+/// those of inline CSS, are left untouched.  Substituted values are not filled again.
+pub fn fill<F>(template: &str, lookup: F) -> String
+where
+    F: Fn(&str) -> Option<String>,
+{
+    let mut filled = String::new();
+    let mut rest = template;
+
+    while let Some(start) = rest.find('{') {
+        filled.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let value = match rest[1..].find('}') {
+            Some(end) if is_name(&rest[1..end + 1]) => lookup(&rest[1..end + 1]).map(|v| (v, end + 2)),
+            _ => None,
+        };
+
+        match value {
+            Some((v, length)) => {
+                filled.push_str(&v);
+                rest = &rest[length..];
+            },
+            None => {
+                filled.push('{');
+                rest = &rest[1..];
+            },
+        }
+    }
+    filled.push_str(rest);
+
+    filled
+}
+
+
+/// Checks whether a string is a valid placeholder name.
+fn is_name(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_alphanumeric() || "_-.".contains(c))
+}
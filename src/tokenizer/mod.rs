@@ -1,8 +1,10 @@
 //! Provides a simple Cobalt tokenizer.
 
 pub mod token;
+pub mod recogniser;
 
 pub use token::{Token, TokenType};
+pub use recogniser::Recogniser;
 
 use crate::error::{throw, Error};
 
@@ -40,6 +42,7 @@ impl Charstream {
     }
 
     /// Returns the next character in the stream, if available.
+    #[allow(clippy::should_implement_trait)]
     pub fn next(&mut self) -> Option<char> {
         let chr = self.peek();
         self.index += 1;
@@ -62,10 +65,17 @@ pub struct Tokenizer {
 impl Tokenizer {
     /// Constructs a new `Tokenizer` from a `String`.
     pub fn new(source: String) -> Self {
+        Self::with_recognisers(source, &[])
+    }
+
+    /// Constructs a new `Tokenizer` from a `String`, reading custom tokens with the given recognisers.
+    /// 
+    /// Recognisers take precedence over the built-in syntax for their trigger characters.
+    pub fn with_recognisers(source: String, recognisers: &[Box<dyn Recogniser>]) -> Self {
         let mut charstream = Charstream::new(source);
         let mut tokens = Vec::new();
 
-        while let Some(t) = Self::next_token(&mut charstream) {
+        while let Some(t) = Self::next_token(&mut charstream, recognisers) {
             tokens.push(t);
         }
 
@@ -98,13 +108,14 @@ impl Tokenizer {
     }
 
     /// Gets the next token from a character stream, if available.
-    fn next_token(charstream: &mut Charstream) -> Option<Token> {
+    fn next_token(charstream: &mut Charstream, recognisers: &[Box<dyn Recogniser>]) -> Option<Token> {
         Self::skip_whitespace(charstream);
 
-        let nextchar = match charstream.next() {
-            Some(n) => n,
-            None => return None,
-        };
+        let nextchar = charstream.next()?;
+
+        if let Some(r) = recognisers.iter().find(|r| r.trigger() == nextchar) {
+            return Some(r.recognise(charstream));
+        }
 
         let mut current = String::from(nextchar);
 
@@ -202,7 +213,7 @@ impl Tokenizer {
             }
             _ => {
                 while let Some(c) = charstream.peek() {
-                    if CTRL_CHARACTERS.contains(c) {
+                    if CTRL_CHARACTERS.contains(c) || recognisers.iter().any(|r| r.trigger() == c) {
                         break;
                     }
                     charstream.next();
@@ -233,6 +244,7 @@ impl Tokenizer {
     }
 
     /// Gets the next token in the stream, if available.
+    #[allow(clippy::should_implement_trait)]
     pub fn next(&mut self) -> Option<Token> {
         let token = self.peek();
         self.index += 1;
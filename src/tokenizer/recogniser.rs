@@ -0,0 +1,18 @@
+//! Creates an interface for recognisers, which read custom tokens.
+
+use crate::tokenizer::{
+    Charstream,
+    Token,
+};
+
+/// Reads a custom token from the character stream, e.g. `@mention` for the trigger `@`.
+/// 
+/// Recognisers typically produce tokens of type `TokenType::Custom`, which are then
+/// parsed by a `Parselet` registered for the same type.
+pub trait Recogniser: Send + Sync {
+    /// Gets the character that begins the custom token.
+    fn trigger(&self) -> char;
+
+    /// Reads the rest of the token, after its trigger character has been consumed.
+    fn recognise(&self, charstream: &mut Charstream) -> Token;
+}
@@ -1,7 +1,10 @@
 //! Provides an abstraction over tokens and token types.
 
+use serde_derive::{Serialize, Deserialize};
+
 /// Enumerates the types of possible tokens.
-#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
+#[derive(PartialEq, Eq, Hash, Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum TokenType {
     Id,
     Class,
@@ -17,13 +20,18 @@ pub enum TokenType {
     Bracket,
     Brace,
     Fixed,
+
+    // Holds the name of a token type read by a custom `Recogniser`.
+    Custom (String),
 }
 
 
 /// Holds a token's type and value.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Token {
+    #[serde(rename = "type")]
     t: TokenType,
+    #[serde(rename = "value")]
     v: String,
 }
 
@@ -38,7 +46,7 @@ impl Token {
 
     /// Gets the type of a token.
     pub fn get_type(&self) -> TokenType {
-        self.t
+        self.t.to_owned()
     }
 
     /// Gets the value of a token.
@@ -0,0 +1,504 @@
+//! Provides variables, which are interpolated into Cobalt text with `\var{name}`.
+//! 
+//! Variables are read from the front matter of a page, a TOML table between two `+++`
+//! lines at its very beginning, then from the `[vars]` table of `cobalt.toml`, and finally
+//! from the built-ins `site.name`, `page.title`, `page.source`, `build.date`, `cobalt.version`
+//! and `data`, the data tree.  Fields of tables are accessed with dots, e.g. `member.name`.
+//! 
+//! Blocks between `\for{item in list}` and `\endfor{}` are repeated for every item of a list,
+//! and blocks between `\if{condition}`, an optional `\else{}` and `\endif{}` are conditional.
+
+use std::{
+    path::Path,
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use toml::{value::Table, Value};
+
+use crate::{
+    parser::Expression,
+    error::{throw, Error},
+    data::Data,
+    cache::Dependency,
+    pass::{Pass, Context},
+    build::Build,
+};
+
+
+/// Holds the delimiter of front matter.
+const FRONT_MATTER: &str = "+++";
+
+
+/// Substitutes the variables of a page and expands its `\for{...}` and `\if{...}` blocks.
+pub struct VarsPass;
+
+impl Pass for VarsPass {
+    /// Substitutes variables, recording the data files used as dependencies.
+    fn run(&self, expressions: Vec<Expression>, context: &Context) -> Vec<Expression> {
+        let build = context.get_build();
+        let front_matter = context.get_front_matter().to_owned();
+
+        let mut vars = Vars::new(build, context.get_page(), front_matter);
+        let substituted = vars.substitute(expressions);
+
+        for dependency in vars.dependencies() {
+            context.depend(dependency);
+        }
+
+        substituted
+    }
+}
+
+
+/// Holds the variables available to a single page.
+pub struct Vars<'a> {
+    build: &'a Build,
+    values: Table,
+    scopes: Vec<(String, Value)>,
+    data: Option<Arc<Data>>,
+    dated: bool,
+}
+
+impl<'a> Vars<'a> {
+    /// Constructs the variables of a page from the configuration of a build and the page's front matter.
+    /// 
+    /// `page` is the path of the page's source file relative to the source directory.
+    /// The data tree of the build is only used once the page uses it.
+    pub fn new(build: &'a Build, page: &Path, front_matter: Table) -> Self {
+        let config = build.get_config();
+        let mut values = Table::new();
+
+        let mut site = Table::new();
+        site.insert("name".to_string(), Value::String (config.site.name.to_owned()));
+        values.insert("site".to_string(), Value::Table (site));
+
+        let mut page_table = Table::new();
+        page_table.insert("source".to_string(), Value::String (page.display().to_string()));
+        values.insert("page".to_string(), Value::Table (page_table));
+
+        let mut build_table = Table::new();
+        build_table.insert("date".to_string(), Value::String (date()));
+        values.insert("build".to_string(), Value::Table (build_table));
+
+        let mut cobalt = Table::new();
+        cobalt.insert("version".to_string(), Value::String (env!("CARGO_PKG_VERSION").to_string()));
+        values.insert("cobalt".to_string(), Value::Table (cobalt));
+
+        if let Some(v) = &config.vars {
+            values.extend(v.iter().map(|(k, v)| (k.to_owned(), v.to_owned())));
+        }
+        values.extend(front_matter);
+
+        Self {
+            build,
+            values,
+            scopes: Vec::new(),
+            data: None,
+            dated: false,
+        }
+    }
+
+    /// Gets the data files that the page depends on, if it used the data tree,
+    /// and the date of the build, if it used `build.date`.
+    pub fn dependencies(&self) -> Vec<Dependency> {
+        let mut dependencies = match &self.data {
+            Some(d) => d.get_files().iter().map(|f| Dependency::Contents (f.to_owned())).collect(),
+            None => Vec::new(),
+        };
+        if self.data.is_some() {
+            dependencies.push(Dependency::Listing (self.build.get_data().to_path_buf()));
+        }
+        if self.dated {
+            dependencies.push(Dependency::Date);
+        }
+        dependencies
+    }
+
+    /// Looks up a variable by its dotted name, if it is defined.
+    fn lookup(&mut self, name: &str) -> Option<Value> {
+        let mut fields = name.trim().split('.');
+        let first = fields.next()?;
+
+        let value = match self.scopes.iter().rev().find(|(n, _)| n == first) {
+            Some((_, v)) => v,
+            None if first == "data" && !self.values.contains_key("data") => {
+                self.data.get_or_insert_with(|| self.build.get_data_tree()).get_tree()
+            },
+            None => {
+                self.dated |= first == "build";
+                self.values.get(first)?
+            },
+        };
+
+        let mut value = value;
+        for field in fields {
+            value = match value {
+                Value::Table (t) => t.get(field)?,
+                Value::Array (a) => a.get(field.parse::<usize>().ok()?)?,
+                _ => return None,
+            };
+        }
+
+        Some(value.to_owned())
+    }
+
+    /// Gets the value of a variable or throws an error if it is undefined.
+    fn get(&mut self, name: &str) -> Value {
+        match self.lookup(name) {
+            Some(v) => v,
+            None => throw(Error::UndefinedVariable (name.trim().to_string())),
+        }
+    }
+
+    /// Replaces every `\var{...}` in a vector of expressions with the variable's value
+    /// and expands `\for{...}` and `\if{...}` blocks.
+    /// 
+    /// A variable within text is merged with the surrounding text into a single paragraph.
+    /// Variables are also interpolated into headings and hyperlinks.
+    pub fn substitute(&mut self, expressions: Vec<Expression>) -> Vec<Expression> {
+        // As in the emitted page, the last `\pagename{...}` takes precedence.
+        if self.values.get("page").is_none_or(|p| p.get("title").is_none()) {
+            let title = expressions.iter().rev().find_map(|e| match e {
+                Expression::Ctrl { keyword: k, argument: a, .. } if k == "pagename" => Some(a.to_owned()),
+                _ => None,
+            });
+            if let Some(Value::Table (p)) = self.values.get_mut("page") {
+                p.insert("title".to_string(), Value::String (title.unwrap_or_default()));
+            }
+        }
+
+        let mut substituted: Vec<Expression> = Vec::new();
+        let mut joining = false;
+        let mut expressions = expressions.into_iter();
+
+        while let Some(expr) = expressions.next() {
+            let expr = match expr {
+                Expression::Ctrl { keyword: k, argument: a, .. } if k == "var" => {
+                    let value = to_string(&self.get(&a));
+                    match substituted.last_mut() {
+                        Some(Expression::Paragraph (p)) => p.push_str(&value),
+                        _ => substituted.push(Expression::Paragraph (value)),
+                    }
+                    joining = true;
+                    continue;
+                },
+                Expression::Ctrl { keyword: k, argument: a, .. } if k == "for" => {
+                    let (body, _) = block(&mut expressions, "for", &["endfor"]);
+                    substituted.append(&mut self.repeat(&a, body));
+                    joining = false;
+                    continue;
+                },
+                Expression::Ctrl { keyword: k, argument: a, .. } if k == "if" => {
+                    let (then, end) = block(&mut expressions, "if", &["else", "endif"]);
+                    let otherwise = match end.as_str() {
+                        "else" => block(&mut expressions, "if", &["endif"]).0,
+                        _ => Vec::new(),
+                    };
+                    let chosen = if self.condition(&a) { then } else { otherwise };
+                    substituted.append(&mut self.substitute(chosen));
+                    joining = false;
+                    continue;
+                },
+                Expression::Ctrl { keyword: k, .. } if ["endfor", "else", "endif"].contains(&k.as_str()) => {
+                    throw(Error::UnmatchedBlock (k));
+                },
+                Expression::Paragraph (s) if joining => {
+                    joining = false;
+                    match substituted.last_mut() {
+                        Some(Expression::Paragraph (p)) => {
+                            p.push_str(&s);
+                            continue;
+                        },
+                        _ => Expression::Paragraph (s),
+                    }
+                },
+                Expression::Hyperlink { text: t, href: h } => Expression::Hyperlink {
+                    text: self.interpolate(&t),
+                    href: self.interpolate(&h),
+                },
+                Expression::H1 (s) => Expression::H1 (self.interpolate(&s)),
+                Expression::H2 (s) => Expression::H2 (self.interpolate(&s)),
+                Expression::H3 (s) => Expression::H3 (self.interpolate(&s)),
+                Expression::H4 (s) => Expression::H4 (self.interpolate(&s)),
+                Expression::H5 (s) => Expression::H5 (self.interpolate(&s)),
+                Expression::H6 (s) => Expression::H6 (self.interpolate(&s)),
+                Expression::Fixed (e) => Expression::Fixed (self.substitute(e)),
+                Expression::Custom { name: n, value: v, children: c } => Expression::Custom {
+                    name: n,
+                    value: self.interpolate(&v),
+                    children: self.substitute(c),
+                },
+                e => e,
+            };
+            joining = false;
+            substituted.push(expr);
+        }
+
+        substituted
+    }
+
+    /// Expands the body of `\for{item in list}` once for every item of the list.
+    /// 
+    /// Within the body, `item` is the current item and `loop.index` its index, starting at 1.
+    /// Tables are iterated as items with a `key` and a `value`.
+    fn repeat(&mut self, argument: &str, body: Vec<Expression>) -> Vec<Expression> {
+        let (name, list) = match argument.split_once(" in ") {
+            Some((n, l)) => (n.trim().to_string(), l.trim()),
+            None => throw(Error::InvalidCtrlSequence (format!("for{{{}}}", argument))),
+        };
+
+        let items = match self.get(list) {
+            Value::Array (a) => a,
+            Value::Table (t) => t
+                .into_iter()
+                .map(|(k, v)| {
+                    let mut item = Table::new();
+                    item.insert("key".to_string(), Value::String (k));
+                    item.insert("value".to_string(), v);
+                    Value::Table (item)
+                })
+                .collect(),
+            _ => throw(Error::NotAList (list.to_string())),
+        };
+
+        let mut expanded = Vec::new();
+        for (i, item) in items.into_iter().enumerate() {
+            let mut index = Table::new();
+            index.insert("index".to_string(), Value::Integer (i as i64 + 1));
+
+            self.scopes.push(("loop".to_string(), Value::Table (index)));
+            self.scopes.push((name.to_owned(), item));
+            expanded.append(&mut self.substitute(body.to_owned()));
+            self.scopes.truncate(self.scopes.len() - 2);
+        }
+
+        expanded
+    }
+
+    /// Evaluates the condition of `\if{...}`.
+    /// 
+    /// Conditions are a variable, which holds if it is defined and not `false`, `0`
+    /// or empty, its negation `!name`, or a comparison `name == value` or `name != value`.
+    fn condition(&mut self, condition: &str) -> bool {
+        let condition = condition.trim();
+
+        for (operator, equal) in [("==", true), ("!=", false)] {
+            if let Some((name, value)) = condition.split_once(operator) {
+                let value = value.trim().trim_matches('"');
+                return (to_string(&self.get(name)) == value) == equal;
+            }
+        }
+
+        match condition.strip_prefix('!') {
+            Some(c) => !self.condition(c),
+            None => match self.lookup(condition) {
+                Some(Value::Boolean (b)) => b,
+                Some(Value::Integer (i)) => i != 0,
+                Some(Value::String (s)) => !s.is_empty(),
+                Some(Value::Array (a)) => !a.is_empty(),
+                Some(Value::Table (t)) => !t.is_empty(),
+                Some(_) => true,
+                None => false,
+            },
+        }
+    }
+
+    /// Replaces every `\var{...}` within a string with the variable's value.
+    pub fn interpolate(&mut self, s: &str) -> String {
+        let mut interpolated = String::new();
+        let mut rest = s;
+
+        while let Some(start) = rest.find("\\var{") {
+            let end = match rest[start..].find('}') {
+                Some(e) => start + e,
+                None => break,
+            };
+            interpolated.push_str(&rest[..start]);
+            interpolated.push_str(&to_string(&self.get(&rest[start + 5..end])));
+            rest = &rest[end + 1..];
+        }
+        interpolated.push_str(rest);
+
+        interpolated
+    }
+}
+
+
+/// Collects the expressions of a block up to its closing control sequence, accounting for
+/// nested blocks of the same kind.  Returns the block and the keyword that closed it.
+fn block<I>(expressions: &mut I, open: &str, close: &[&str]) -> (Vec<Expression>, String)
+where
+    I: Iterator<Item = Expression>,
+{
+    let end = format!("end{}", open);
+    let mut body = Vec::new();
+    let mut depth = 0;
+
+    for expr in expressions.by_ref() {
+        if let Expression::Ctrl { keyword: k, .. } = &expr {
+            if depth == 0 && close.contains(&k.as_str()) {
+                return (body, k.to_owned());
+            }
+            if *k == open {
+                depth += 1;
+            } else if *k == end {
+                depth -= 1;
+            }
+        }
+        body.push(expr);
+    }
+
+    throw(Error::UnmatchedBlock (open.to_string()));
+}
+
+
+/// Splits the front matter from the source of a page, if it has any.
+pub fn front_matter(data: &str) -> (Table, &str) {
+    let body = match data.strip_prefix(FRONT_MATTER) {
+        Some(b) if b.starts_with('\n') || b.starts_with("\r\n") => b,
+        _ => return (Table::new(), data),
+    };
+
+    let (front_matter, rest) = match body.find(&format!("\n{}", FRONT_MATTER)) {
+        Some(i) => (&body[..i], &body[i + 1 + FRONT_MATTER.len()..]),
+        None => throw(Error::InvalidFrontMatter ("missing closing '+++'".to_string())),
+    };
+
+    match toml::from_str(front_matter) {
+        Ok(t) => (t, rest),
+        Err(e) => throw(Error::InvalidFrontMatter (e.to_string())),
+    }
+}
+
+
+/// Converts a TOML value to the text it is interpolated as.
+fn to_string(value: &Value) -> String {
+    match value {
+        Value::String (s) => s.to_owned(),
+        v => v.to_string(),
+    }
+}
+
+
+/// Gets the current date in the format `YYYY-MM-DD`.
+pub fn date() -> String {
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+
+    civil_date(seconds)
+}
+
+
+/// Gets the date, in the format `YYYY-MM-DD`, of a number of seconds since 1970-01-01.
+fn civil_date(seconds: u64) -> String {
+    // Converts days since 1970-01-01 into a civil date, see Howard Hinnant's `civil_from_days`.
+    let days = (seconds / 86400) as i64 + 719468;
+    let era = days / 146097;
+    let day_of_era = days - era * 146097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + (month <= 2) as i64;
+
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::catch;
+
+    #[test]
+    fn front_matter_is_split_from_the_body() {
+        let (table, body) = front_matter("+++\ntitle = \"Home\"\ntags = [\"a\"]\n+++\n# Home\n");
+        assert_eq!(table.get("title"), Some(&Value::String ("Home".to_string())));
+        assert!(matches!(table.get("tags"), Some(Value::Array (_))));
+        assert_eq!(body, "\n# Home\n");
+    }
+
+    #[test]
+    fn front_matter_is_optional() {
+        let (table, body) = front_matter("# Home\n+++\n");
+        assert!(table.is_empty());
+        assert_eq!(body, "# Home\n+++\n");
+
+        // The delimiter must stand alone on the first line.
+        let (table, body) = front_matter("+++ not front matter\n");
+        assert!(table.is_empty());
+        assert_eq!(body, "+++ not front matter\n");
+    }
+
+    #[test]
+    fn front_matter_must_be_closed_and_valid() {
+        let unclosed = catch(|| front_matter("+++\ntitle = \"Home\"\n"));
+        assert!(matches!(unclosed, Err(Error::InvalidFrontMatter (_))));
+
+        let invalid = catch(|| front_matter("+++\ntitle = \n+++\n"));
+        assert!(matches!(invalid, Err(Error::InvalidFrontMatter (_))));
+    }
+
+    #[test]
+    fn civil_date_converts_seconds() {
+        assert_eq!(civil_date(0), "1970-01-01");
+        assert_eq!(civil_date(951_782_400), "2000-02-29");
+        assert_eq!(civil_date(1_709_251_199), "2024-02-29");
+        assert_eq!(civil_date(1_735_689_600), "2025-01-01");
+    }
+
+    #[test]
+    fn date_has_the_expected_format() {
+        let date = date();
+        assert_eq!(date.len(), 10);
+        assert_eq!(date.as_bytes()[4], b'-');
+        assert_eq!(date.as_bytes()[7], b'-');
+    }
+
+    fn ctrl(keyword: &str) -> Expression {
+        Expression::Ctrl {
+            keyword: keyword.to_string(),
+            class: None,
+            id: None,
+            argument: String::new(),
+        }
+    }
+
+    fn paragraph(text: &str) -> Expression {
+        Expression::Paragraph (text.to_string())
+    }
+
+    #[test]
+    fn block_collects_up_to_its_closing_sequence() {
+        let mut expressions = vec![paragraph("a"), ctrl("endif"), paragraph("b")].into_iter();
+        let (body, close) = block(&mut expressions, "if", &["else", "endif"]);
+        assert_eq!(format!("{:?}", body), format!("{:?}", vec![paragraph("a")]));
+        assert_eq!(close, "endif");
+        assert_eq!(expressions.count(), 1);
+    }
+
+    #[test]
+    fn block_skips_nested_blocks() {
+        let mut expressions = vec![
+            ctrl("if"), paragraph("a"), ctrl("else"), ctrl("endif"),
+            ctrl("else"), paragraph("b"), ctrl("endif"),
+        ].into_iter();
+        let (body, close) = block(&mut expressions, "if", &["else", "endif"]);
+        assert_eq!(body.len(), 4);
+        assert_eq!(close, "else");
+
+        let (body, close) = block(&mut expressions, "if", &["endif"]);
+        assert_eq!(format!("{:?}", body), format!("{:?}", vec![paragraph("b")]));
+        assert_eq!(close, "endif");
+    }
+
+    #[test]
+    fn block_must_be_closed() {
+        let mut expressions = vec![ctrl("for"), ctrl("endfor")].into_iter();
+        let unmatched = catch(|| block(&mut expressions, "for", &["endfor"]));
+        assert!(matches!(unmatched, Err(Error::UnmatchedBlock (k)) if k == "for"));
+    }
+}
@@ -0,0 +1,211 @@
+//! Provides watch mode, which polls the site for changes and rebuilds it incrementally.
+
+use std::{
+    collections::BTreeMap,
+    ffi::OsStr,
+    fs,
+    path::{Path, PathBuf},
+    thread,
+    time::{Duration, Instant, SystemTime},
+};
+
+use colored::*;
+use walkdir::WalkDir;
+
+use crate::{
+    build::Build,
+    error::{self, catch},
+    ignore,
+    include,
+    log,
+};
+
+
+/// Holds the interval between two polls of the file system.
+const INTERVAL: Duration = Duration::from_millis(500);
+
+
+/// Holds the modification time and size of every watched file.
+type Snapshot = BTreeMap<PathBuf, (Option<SystemTime>, u64)>;
+
+
+/// Enumerates the kinds of rebuild needed after a change.
+#[derive(PartialEq, Eq, Debug)]
+enum Rebuild {
+    Nothing,
+    Pages (Vec<PathBuf>),
+    Site,
+    Config,
+}
+
+
+/// Watches the site and rebuilds it whenever its sources change, until the process is stopped.
+/// 
+/// `load` reads the configuration and prepares a build; it is called again whenever
+/// the configuration changes.  `on_rebuild` is called with the current build and the
+/// result of every rebuild.
+/// 
+/// After a failed rebuild, the next change rebuilds the whole site, since the pages
+/// changed before the failure were never written.
+pub fn watch<L, F>(load: L, mut on_rebuild: F) -> !
+where
+    L: Fn() -> Build,
+    F: FnMut(&Build, Result<(), error::Error>),
+{
+    let mut build = match catch(&load) {
+        Ok(b) => b,
+        Err(e) => error::throw(e),
+    };
+    let result = rebuild(|| build.run());
+    let mut failed = result.is_err();
+    on_rebuild(&build, result);
+
+    let mut state = snapshot(&build);
+    log::info(&format!("{} {} for changes", "Watching".cyan().bold(), build.get_source().display()));
+
+    loop {
+        thread::sleep(INTERVAL);
+
+        let current = snapshot(&build);
+        let changed = changes(&build, &state, &current);
+        state = current;
+
+        let changed = match changed {
+            Rebuild::Pages (_) if failed => Rebuild::Site,
+            c => c,
+        };
+
+        let result = match changed {
+            Rebuild::Nothing => continue,
+            Rebuild::Pages (pages) => {
+                let start = Instant::now();
+                let result = rebuild(|| build.run_pages(&pages));
+                if result.is_ok() {
+                    log::info(&format!(
+                        "{} {} page(s) in {} ms",
+                        "Rebuilt".green().bold(),
+                        pages.len(),
+                        start.elapsed().as_millis(),
+                    ));
+                }
+                result
+            },
+            Rebuild::Site => rebuild(|| build.run()),
+            Rebuild::Config => match rebuild(&load) {
+                Ok(b) => {
+                    log::info(&format!("{} configuration", "Reloaded".cyan().bold()));
+                    build = b;
+                    state = snapshot(&build);
+                    rebuild(|| build.run())
+                },
+                Err(e) => Err(e),
+            },
+        };
+
+        failed = result.is_err();
+        on_rebuild(&build, result);
+    }
+}
+
+
+/// Runs a step of a rebuild, printing its error without exiting if it fails.
+fn rebuild<T, F: FnOnce() -> T>(f: F) -> Result<T, error::Error> {
+    let result = catch(f);
+
+    if let Err(e) = &result {
+        error::report(e);
+    }
+
+    result
+}
+
+
+/// Records the state of the configuration file and the source, layout, shortcode, data and static directories.
+fn snapshot(build: &Build) -> Snapshot {
+    let mut snapshot = Snapshot::new();
+
+    let mut directories = vec![build.get_source(), build.get_layouts(), build.get_shortcodes(), build.get_data()];
+    if let Some(s) = build.get_static() {
+        directories.push(s);
+    }
+
+    for directory in directories {
+        let walker = WalkDir::new(directory)
+            .follow_links(true)
+            .into_iter()
+            .filter_entry(|e| e.depth() == 0 || !is_ignored(build, e.path(), directory));
+
+        for entry in walker.filter_map(|e| e.ok()) {
+            let relative = entry.path().strip_prefix(directory).unwrap_or(entry.path());
+            if entry.file_type().is_file() && !build.get_rules().is_excluded(relative) {
+                record(&mut snapshot, entry.path());
+            }
+        }
+    }
+    record(&mut snapshot, &build.get_root().join("cobalt.toml"));
+    record(&mut snapshot, &build.get_root().join(ignore::IGNORE_FILE));
+
+    snapshot
+}
+
+
+/// Checks whether a path should not be watched: ignored files and the output directory.
+fn is_ignored(build: &Build, path: &Path, directory: &Path) -> bool {
+    let relative = path.strip_prefix(directory).unwrap_or(path);
+    let rules = build.get_rules();
+    let excluded = rules.is_excluded(relative) && !(path.is_dir() && rules.may_include_within(relative));
+    let output = build.get_output() != build.get_source() && path.starts_with(build.get_output());
+    let reserved = directory == build.get_source() && build.is_reserved(path);
+
+    excluded || output || reserved
+}
+
+
+/// Records the modification time and size of a file, if it exists.
+fn record(snapshot: &mut Snapshot, path: &Path) {
+    if let Ok(m) = fs::metadata(path) {
+        snapshot.insert(path.to_path_buf(), (m.modified().ok(), m.len()));
+    }
+}
+
+
+/// Determines what needs to be rebuilt from the differences between two snapshots.
+/// 
+/// Changed pages are rebuilt alone.  New and deleted pages and changed assets require a
+/// full rebuild to update the sitemap and to prune and copy outputs, and a changed
+/// configuration requires a reload.
+fn changes(build: &Build, old: &Snapshot, new: &Snapshot) -> Rebuild {
+    let config = build.get_root().join("cobalt.toml");
+    let ignore = build.get_root().join(ignore::IGNORE_FILE);
+    let mut pages = Vec::new();
+    let mut site = false;
+
+    let changed = new.iter().filter(|(p, s)| old.get(*p) != Some(s)).map(|(p, _)| p);
+    let deleted = old.keys().filter(|p| !new.contains_key(*p));
+
+    for path in changed {
+        if *path == config || *path == ignore {
+            return Rebuild::Config;
+        }
+        let page = path.extension() == Some(OsStr::new("co")) && !build.is_reserved(path);
+        match path.strip_prefix(build.get_source()) {
+            Ok(p) if page && !include::is_partial(p) && old.contains_key(path) => pages.push(p.to_path_buf()),
+            _ => site = true,
+        }
+    }
+
+    for path in deleted {
+        if *path == config || *path == ignore {
+            return Rebuild::Config;
+        }
+        site = true;
+    }
+
+    if site {
+        Rebuild::Site
+    } else if !pages.is_empty() {
+        Rebuild::Pages (pages)
+    } else {
+        Rebuild::Nothing
+    }
+}